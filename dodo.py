@@ -16,7 +16,7 @@ def task_electrolysis_thys():
             'name': str(crate),
             'actions': [['cargo', 'run', crate]],
             'file_dep': [p / 'config.toml', 'target/debug/electrolysis'],
-            'targets': [p / 'generated.lean'],
+            'targets': [p / 'generated.lean'] + ([p / 'target.lean'] if crate == 'core' else []),
         }
 
 def task_linja_thys():
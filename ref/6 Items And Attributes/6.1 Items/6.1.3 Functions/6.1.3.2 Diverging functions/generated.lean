@@ -11,12 +11,12 @@ open [class] nat
 open [notation] prod.ops
 open [notation] unit
 
-definition test.my_err.«$_FILE_LINE» : sem (string × u32) :=
-let' ret ← ("ref/6 Items And Attributes/6.1 Items/6.1.3 Functions/6.1.3.2 Diverging functions/lib.rs", (3 : nat));
+definition test.my_err.«$_FILE_LINE» : sem ((slice u8) × u32) :=
+let' ret ← ([(114 : nat), (101 : nat), (102 : nat), (47 : nat), (54 : nat), (32 : nat), (73 : nat), (116 : nat), (101 : nat), (109 : nat), (115 : nat), (32 : nat), (65 : nat), (110 : nat), (100 : nat), (32 : nat), (65 : nat), (116 : nat), (116 : nat), (114 : nat), (105 : nat), (98 : nat), (117 : nat), (116 : nat), (101 : nat), (115 : nat), (47 : nat), (54 : nat), (46 : nat), (49 : nat), (32 : nat), (73 : nat), (116 : nat), (101 : nat), (109 : nat), (115 : nat), (47 : nat), (54 : nat), (46 : nat), (49 : nat), (46 : nat), (51 : nat), (32 : nat), (70 : nat), (117 : nat), (110 : nat), (99 : nat), (116 : nat), (105 : nat), (111 : nat), (110 : nat), (115 : nat), (47 : nat), (54 : nat), (46 : nat), (49 : nat), (46 : nat), (51 : nat), (46 : nat), (50 : nat), (32 : nat), (68 : nat), (105 : nat), (118 : nat), (101 : nat), (114 : nat), (103 : nat), (105 : nat), (110 : nat), (103 : nat), (32 : nat), (102 : nat), (117 : nat), (110 : nat), (99 : nat), (116 : nat), (105 : nat), (111 : nat), (110 : nat), (115 : nat), (47 : nat), (108 : nat), (105 : nat), (98 : nat), (46 : nat), (114 : nat), (115 : nat)], (3 : nat));
 return (ret)
 
 
-definition test.my_err (sₐ : string) : sem (empty) :=
+definition test.my_err (sₐ : (slice u8)) : sem (empty) :=
 let' «s$2» ← sₐ;
 let' t6 ← test.my_err.«$_FILE_LINE»;
 let' t5 ← t6;
@@ -31,7 +31,7 @@ if t3 = bool.tt then
 let' ret ← (42 : int);
 return (ret)
 else
-let' t11 ← "Bad number!";
+let' t11 ← [(66 : nat), (97 : nat), (100 : nat), (32 : nat), (110 : nat), (117 : nat), (109 : nat), (98 : nat), (101 : nat), (114 : nat), (33 : nat)];
 let' t10 ← t11;
 mzero
 
@@ -16,7 +16,7 @@ let' «x$3» ← xₐ;
 let' «y$4» ← yₐ;
 let' t5 ← «x$3»;
 let' t6 ← «y$4»;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.sadd i32.bits t5 t6);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.sadd i32.bits t5 t6);
 let' t7 ← «$tmp0»;
 let' ret ← t7.1;
 return (ret)
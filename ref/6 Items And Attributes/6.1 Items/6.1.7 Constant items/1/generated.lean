@@ -12,14 +12,14 @@ open [notation] prod.ops
 open [notation] unit
 
 definition test.BIT1 : sem u32 :=
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.shls u32.bits (1 : nat) (0 : int));
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.shls u32.bits (1 : nat) (0 : int));
 let' t1 ← «$tmp0»;
 let' ret ← t1.1;
 return (ret)
 
 
 definition test.BIT2 : sem u32 :=
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.shls u32.bits (1 : nat) (1 : int));
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.shls u32.bits (1 : nat) (1 : int));
 let' t1 ← «$tmp0»;
 let' ret ← t1.1;
 return (ret)
@@ -33,14 +33,14 @@ let' ret ← «$tmp0»;
 return (ret)
 
 
-definition test.STRING : sem string :=
-let' ret ← "bitstring";
+definition test.STRING : sem (slice u8) :=
+let' ret ← [(98 : nat), (105 : nat), (116 : nat), (115 : nat), (116 : nat), (114 : nat), (105 : nat), (110 : nat), (103 : nat)];
 return (ret)
 
 
 structure test.BitsNStrings := mk {} ::
 (mybits : (array u32 2))
-(mystring : string)
+(mystring : (slice u8))
 
 definition test.BITS_N_STRINGS : sem (test.BitsNStrings) :=
 do «$tmp0» ← test.STRING;
@@ -0,0 +1,35 @@
+import core.generated
+
+noncomputable theory
+
+open bool
+open [class] classical
+open [notation] function
+open [class] int
+open [notation] list
+open [class] nat
+open [notation] prod.ops
+open [notation] unit
+
+definition test.first (sₐ : (slice u8)) : sem (option.Option char32) :=
+let' «s$1» ← sₐ;
+let' t3 ← «s$1»;
+dostep «$tmp» ← @core.«str as core.str.StrExt».chars t3;
+let' t2 ← «$tmp»;
+let' t4 ← @lens.id core.str.Chars;
+do «$tmp0» ← lens.get t4 t2;
+dostep «$tmp» ← @core.«core.str.Chars<'a> as core.iter.iterator.Iterator».next «$tmp0»;
+match «$tmp» with (ret, «t4$») :=
+do t2 ← lens.set t4 t2 «t4$»;
+return (ret)
+end
+
+
+definition test.lambda : sem (option.Option char32) :=
+let' t2 ← [(206 : nat), (187 : nat)];
+let' t1 ← t2;
+dostep «$tmp» ← @test.first t1;
+let' ret ← «$tmp»;
+return (ret)
+
+
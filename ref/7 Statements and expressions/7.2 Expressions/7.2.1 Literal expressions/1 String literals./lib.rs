@@ -0,0 +1,7 @@
+fn first(s: &str) -> Option<char> {
+    s.chars().next()
+}
+
+fn lambda() -> Option<char> {
+    first("λ")
+}
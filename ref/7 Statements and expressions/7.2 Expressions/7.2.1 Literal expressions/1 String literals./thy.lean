@@ -0,0 +1,4 @@
+import .generated
+
+-- 'λ' is U+03BB, encoded as `0xCE 0xBB`
+example : sem.terminates_with (λ r, r = option.Option.Some 955) test.lambda := rfl
@@ -13,7 +13,7 @@ open [notation] unit
 
 definition test.main : sem (unit) :=
 let' t1 ← ⋆;
-let' t2 ← "hello";
+let' t2 ← [(104 : nat), (101 : nat), (108 : nat), (108 : nat), (111 : nat)];
 let' t3 ← (5 : int);
 let' ret ← ⋆;
 return (⋆)
@@ -0,0 +1,38 @@
+import psem
+import core.generated
+
+noncomputable theory
+
+open bool
+open [class] classical
+open [notation] function
+open [class] int
+open [notation] list
+open [class] nat
+open [notation] prod.ops
+open [notation] unit
+
+definition test.add (xₐ : u8) (yₐ : u8) : psem (u8) :=
+let' «x$3» ← xₐ;
+let' «y$4» ← yₐ;
+let' t5 ← «x$3»;
+let' t6 ← «y$4»;
+pdo «$tmp0» ← overflowing.add u8.bits t5 t6;
+let' t7 ← «$tmp0»;
+psem.assert (panic_reason.overflow "add") (bool.bnot t7.2) (
+let' ret ← t7.1;
+psem.return (ret))
+
+
+definition test.div (xₐ : u32) (yₐ : u32) : psem (u32) :=
+let' «x$3» ← xₐ;
+let' «y$4» ← yₐ;
+let' t5 ← «x$3»;
+let' t6 ← «y$4»;
+let' t7 ← t6 =ᵇ (0 : nat);
+psem.assert panic_reason.div_by_zero (bool.bnot t7) (
+pdo «$tmp0» ← checked.div u32.bits t5 t6;
+let' ret ← «$tmp0»;
+psem.return (ret))
+
+
@@ -0,0 +1,7 @@
+fn add(x: u8, y: u8) -> u8 {
+    x + y
+}
+
+fn div(x: u32, y: u32) -> u32 {
+    x / y
+}
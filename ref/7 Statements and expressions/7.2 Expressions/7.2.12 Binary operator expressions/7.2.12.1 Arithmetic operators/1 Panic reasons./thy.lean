@@ -0,0 +1,4 @@
+import .generated
+
+example : test.add 255 1 = psem.panic (panic_reason.overflow "add") := rfl
+example : test.div 1 0 = psem.panic panic_reason.div_by_zero := rfl
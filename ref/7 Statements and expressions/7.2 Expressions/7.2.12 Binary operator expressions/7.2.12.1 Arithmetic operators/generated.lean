@@ -16,17 +16,17 @@ let' «x$3» ← xₐ;
 let' «y$4» ← yₐ;
 let' t6 ← «x$3»;
 let' t7 ← «y$4»;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.add u32.bits t6 t7);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.add u32.bits t6 t7);
 let' t8 ← «$tmp0»;
 let' t5 ← t8.1;
 let' t10 ← «x$3»;
 let' t11 ← «y$4»;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.sub u32.bits t10 t11);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.sub u32.bits t10 t11);
 let' t12 ← «$tmp0»;
 let' t9 ← t12.1;
 let' t14 ← «x$3»;
 let' t15 ← «y$4»;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.mul u32.bits t14 t15);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.mul u32.bits t14 t15);
 let' t16 ← «$tmp0»;
 let' t13 ← t16.1;
 let' t18 ← «x$3»;
@@ -48,17 +48,17 @@ let' «x$3» ← xₐ;
 let' «y$4» ← yₐ;
 let' t6 ← «x$3»;
 let' t7 ← «y$4»;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.sadd i32.bits t6 t7);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.sadd i32.bits t6 t7);
 let' t8 ← «$tmp0»;
 let' t5 ← t8.1;
 let' t10 ← «x$3»;
 let' t11 ← «y$4»;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.ssub i32.bits t10 t11);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.ssub i32.bits t10 t11);
 let' t12 ← «$tmp0»;
 let' t9 ← t12.1;
 let' t14 ← «x$3»;
 let' t15 ← «y$4»;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.smul i32.bits t14 t15);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.smul i32.bits t14 t15);
 let' t16 ← «$tmp0»;
 let' t13 ← t16.1;
 let' t18 ← «x$3»;
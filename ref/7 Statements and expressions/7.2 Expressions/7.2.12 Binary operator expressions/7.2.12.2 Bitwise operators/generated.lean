@@ -25,12 +25,12 @@ let' t13 ← «y$4»;
 let' t11 ← bitxor u32.bits t12 t13;
 let' t15 ← «x$3»;
 let' t16 ← «y$4»;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.shl u32.bits t15 t16);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.shl u32.bits t15 t16);
 let' t17 ← «$tmp0»;
 let' t14 ← t17.1;
 let' t19 ← «x$3»;
 let' t20 ← «y$4»;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.shr u32.bits t19 t20);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.shr u32.bits t19 t20);
 let' t21 ← «$tmp0»;
 let' t18 ← t21.1;
 let' ret ← ⋆;
@@ -51,12 +51,12 @@ let' t13 ← «y$4»;
 let' t11 ← sbitxor i32.bits t12 t13;
 let' t15 ← «x$3»;
 let' t16 ← «y$4»;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.sshls i32.bits t15 t16);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.sshls i32.bits t15 t16);
 let' t17 ← «$tmp0»;
 let' t14 ← t17.1;
 let' t19 ← «x$3»;
 let' t20 ← «y$4»;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.sshrs i32.bits t19 t20);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.sshrs i32.bits t19 t20);
 let' t21 ← «$tmp0»;
 let' t18 ← t21.1;
 let' ret ← ⋆;
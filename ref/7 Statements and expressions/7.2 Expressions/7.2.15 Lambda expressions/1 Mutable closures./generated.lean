@@ -43,12 +43,12 @@ structure test.foo.closure_13 (U0 : Type₁) := (val : U0)
 
 definition test.foo.closure_13.fn («$a1» : (test.foo.closure_13 i32)) (xₐ : i32) : sem (i32 × (test.foo.closure_13 i32)) :=
 let' «x$3» ← xₐ;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.sadd i32.bits (test.foo.closure_13.val «$a1») (1 : int));
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.sadd i32.bits (test.foo.closure_13.val «$a1») (1 : int));
 let' t4 ← «$tmp0»;
 let' «$a1» ← test.foo.closure_13.mk t4.1;
 let' t5 ← «x$3»;
 let' t6 ← (test.foo.closure_13.val «$a1»);
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.sadd i32.bits t5 t6);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.sadd i32.bits t5 t6);
 let' t7 ← «$tmp0»;
 let' ret ← t7.1;
 return (ret, «$a1»)
@@ -32,7 +32,7 @@ definition test.foo.closure_13.fn («$a1» : (test.foo.closure_13 i32)) (xₐ :
 let' «x$3» ← xₐ;
 let' t4 ← «x$3»;
 let' t5 ← (test.foo.closure_13.val «$a1»);
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.sadd i32.bits t4 t5);
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.sadd i32.bits t4 t5);
 let' t6 ← «$tmp0»;
 let' ret ← t6.1;
 return (ret)
@@ -39,7 +39,7 @@ return (⋆)
 ;
 return (sum.inr tmp__)else
 let' t15 ← ⋆;
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.ssub i32.bits «x$2» (1 : int));
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.ssub i32.bits «x$2» (1 : int));
 let' t20 ← «$tmp0»;
 let' «x$2» ← t20.1;
 let' t5 ← ⋆;
@@ -17,12 +17,12 @@ let' «c$4» ← cₐ;
 let' «x$5» ← (0 : int);
 let' t7 ← «b$3»;
 if t7 = bool.tt then
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.sadd i32.bits «x$5» (1 : int));
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.sadd i32.bits «x$5» (1 : int));
 let' t8 ← «$tmp0»;
 let' «x$5» ← t8.1;
 let' t10 ← «c$4»;
 if t10 = bool.tt then
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.sadd i32.bits «x$5» (1 : int));
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.sadd i32.bits «x$5» (1 : int));
 let' t11 ← «$tmp0»;
 let' «x$5» ← t11.1;
 let' t12 ← «x$5»;
@@ -37,7 +37,7 @@ else
 let' t6 ← ⋆;
 let' t10 ← «c$4»;
 if t10 = bool.tt then
-do «$tmp0» ← sem.map (λx, (x, tt)) (checked.sadd i32.bits «x$5» (1 : int));
+do «$tmp0» ← sem.map (λx, (x, ff)) (checked.sadd i32.bits «x$5» (1 : int));
 let' t11 ← «$tmp0»;
 let' «x$5» ← t11.1;
 let' t12 ← «x$5»;
@@ -11,45 +11,45 @@ open [class] nat
 open [notation] prod.ops
 open [notation] unit
 
-definition test.f1 (xₐ : i32) : sem (string) :=
+definition test.f1 (xₐ : i32) : sem ((slice u8)) :=
 let' «x$2» ← xₐ;
 match «x$2» with
 | 1 :=
-let' ret ← "one";
+let' ret ← [(111 : nat), (110 : nat), (101 : nat)];
 return (ret)
 | 2 :=
-let' ret ← "two";
+let' ret ← [(116 : nat), (119 : nat), (111 : nat)];
 return (ret)
 | 3 :=
-let' ret ← "three";
+let' ret ← [(116 : nat), (104 : nat), (114 : nat), (101 : nat), (101 : nat)];
 return (ret)
 | 4 :=
-let' ret ← "four";
+let' ret ← [(102 : nat), (111 : nat), (117 : nat), (114 : nat)];
 return (ret)
 | 5 :=
-let' ret ← "five";
+let' ret ← [(102 : nat), (105 : nat), (118 : nat), (101 : nat)];
 return (ret)
 | _ :=
-let' ret ← "something else";
+let' ret ← [(115 : nat), (111 : nat), (109 : nat), (101 : nat), (116 : nat), (104 : nat), (105 : nat), (110 : nat), (103 : nat), (32 : nat), (101 : nat), (108 : nat), (115 : nat), (101 : nat)];
 return (ret)
 
 end
 
 
-definition test.f2 (xₐ : i32) : sem (string) :=
+definition test.f2 (xₐ : i32) : sem ((slice u8)) :=
 let' «x$2» ← xₐ;
 let' t4 ← (1 : int) ≤ᵇ «x$2»;
 if t4 = bool.tt then
 let' t5 ← «x$2» ≤ᵇ (5 : int);
 if t5 = bool.tt then
 let' «e$3» ← «x$2»;
-let' ret ← "got a range element";
+let' ret ← [(103 : nat), (111 : nat), (116 : nat), (32 : nat), (97 : nat), (32 : nat), (114 : nat), (97 : nat), (110 : nat), (103 : nat), (101 : nat), (32 : nat), (101 : nat), (108 : nat), (101 : nat), (109 : nat), (101 : nat), (110 : nat), (116 : nat)];
 return (ret)
 else
-let' ret ← "anything";
+let' ret ← [(97 : nat), (110 : nat), (121 : nat), (116 : nat), (104 : nat), (105 : nat), (110 : nat), (103 : nat)];
 return (ret)
 else
-let' ret ← "anything";
+let' ret ← [(97 : nat), (110 : nat), (121 : nat), (116 : nat), (104 : nat), (105 : nat), (110 : nat), (103 : nat)];
 return (ret)
 
 
@@ -57,27 +57,27 @@ definition test.f3 (xₐ : i32) : sem (unit) :=
 let' «x$2» ← xₐ;
 match «x$2» with
 | 0 :=
-let' «y$3» ← "zero";
+let' «y$3» ← [(122 : nat), (101 : nat), (114 : nat), (111 : nat)];
 match «x$2» with
 | 0 :=
-let' «z$4» ← "zero";
+let' «z$4» ← [(122 : nat), (101 : nat), (114 : nat), (111 : nat)];
 let' ret ← ⋆;
 return (⋆)
 | _ :=
-let' «z$4» ← "some";
+let' «z$4» ← [(115 : nat), (111 : nat), (109 : nat), (101 : nat)];
 let' ret ← ⋆;
 return (⋆)
 
 end
 | _ :=
-let' «y$3» ← "some";
+let' «y$3» ← [(115 : nat), (111 : nat), (109 : nat), (101 : nat)];
 match «x$2» with
 | 0 :=
-let' «z$4» ← "zero";
+let' «z$4» ← [(122 : nat), (101 : nat), (114 : nat), (111 : nat)];
 let' ret ← ⋆;
 return (⋆)
 | _ :=
-let' «z$4» ← "some";
+let' «z$4» ← [(115 : nat), (111 : nat), (109 : nat), (101 : nat)];
 let' ret ← ⋆;
 return (⋆)
 
@@ -90,11 +90,11 @@ definition test.f4 (xₐ : i32) : sem (unit) :=
 let' «x$2» ← xₐ;
 match «x$2» with
 | 0 :=
-let' «message$3» ← "not many";
+let' «message$3» ← [(110 : nat), (111 : nat), (116 : nat), (32 : nat), (109 : nat), (97 : nat), (110 : nat), (121 : nat)];
 let' ret ← ⋆;
 return (⋆)
 | 1 :=
-let' «message$3» ← "not many";
+let' «message$3» ← [(110 : nat), (111 : nat), (116 : nat), (32 : nat), (109 : nat), (97 : nat), (110 : nat), (121 : nat)];
 let' ret ← ⋆;
 return (⋆)
 | _ :=
@@ -102,23 +102,23 @@ let' t4 ← (2 : int) ≤ᵇ «x$2»;
 if t4 = bool.tt then
 let' t5 ← «x$2» ≤ᵇ (9 : int);
 if t5 = bool.tt then
-let' «message$3» ← "a few";
+let' «message$3» ← [(97 : nat), (32 : nat), (102 : nat), (101 : nat), (119 : nat)];
 let' ret ← ⋆;
 return (⋆)
 else
-let' «message$3» ← "lots";
+let' «message$3» ← [(108 : nat), (111 : nat), (116 : nat), (115 : nat)];
 let' ret ← ⋆;
 return (⋆)
 else
-let' «message$3» ← "lots";
+let' «message$3» ← [(108 : nat), (111 : nat), (116 : nat), (115 : nat)];
 let' ret ← ⋆;
 return (⋆)
 
 end
 
 
-definition test.f5.«$_FILE_LINE» : sem (string × u32) :=
-let' ret ← ("ref/7 Statements and expressions/7.2 Expressions/7.2.22 Match expressions/lib.rs", (36 : nat));
+definition test.f5.«$_FILE_LINE» : sem ((slice u8) × u32) :=
+let' ret ← ([(114 : nat), (101 : nat), (102 : nat), (47 : nat), (55 : nat), (32 : nat), (83 : nat), (116 : nat), (97 : nat), (116 : nat), (101 : nat), (109 : nat), (101 : nat), (110 : nat), (116 : nat), (115 : nat), (32 : nat), (97 : nat), (110 : nat), (100 : nat), (32 : nat), (101 : nat), (120 : nat), (112 : nat), (114 : nat), (101 : nat), (115 : nat), (115 : nat), (105 : nat), (111 : nat), (110 : nat), (115 : nat), (47 : nat), (55 : nat), (46 : nat), (50 : nat), (32 : nat), (69 : nat), (120 : nat), (112 : nat), (114 : nat), (101 : nat), (115 : nat), (115 : nat), (105 : nat), (111 : nat), (110 : nat), (115 : nat), (47 : nat), (55 : nat), (46 : nat), (50 : nat), (46 : nat), (50 : nat), (50 : nat), (32 : nat), (77 : nat), (97 : nat), (116 : nat), (99 : nat), (104 : nat), (32 : nat), (101 : nat), (120 : nat), (112 : nat), (114 : nat), (101 : nat), (115 : nat), (115 : nat), (105 : nat), (111 : nat), (110 : nat), (115 : nat), (47 : nat), (108 : nat), (105 : nat), (98 : nat), (46 : nat), (114 : nat), (115 : nat)], (36 : nat));
 return (ret)
 
 
@@ -13,7 +13,7 @@ open [notation] unit
 
 definition test.main : sem (unit) :=
 let' t1 ← ((0 : int), (4 : int));
-let' t2 ← ("a", (4 : nat), tt);
+let' t2 ← ([(97 : nat)], (4 : nat), tt);
 let' ret ← ⋆;
 return (⋆)
 
@@ -22,7 +22,7 @@ inductive test.main.TuplePoint :=
 mk {} : i64 → i64 → test.main.TuplePoint
 
 structure test.main.game.User := mk {} ::
-(name : string)
+(name : (slice u8))
 (age : u32)
 (score : usize)
 
@@ -38,7 +38,7 @@ definition test.main : sem (unit) :=
 let' t1 ← test.main.Point.mk (10 : int) (20 : int);
 let' t2 ← test.main.NothingInMe.mk;
 let' t3 ← test.main.TuplePoint.mk (10 : int) (20 : int);
-let' t6 ← "Joe";
+let' t6 ← [(74 : nat), (111 : nat), (101 : nat)];
 let' t5 ← t6;
 let' «u$4» ← test.main.game.User.mk t5 (35 : nat) (100000 : nat);
 let' t8 ← test.main.Cookie.mk;
@@ -13,7 +13,7 @@ open [notation] unit
 
 definition test.main : sem (unit) :=
 let' t1 ← [(1 : int), (2 : int), (3 : int), (4 : int)];
-let' t2 ← ["a", "b", "c", "d"];
+let' t2 ← [[(97 : nat)], [(98 : nat)], [(99 : nat)], [(100 : nat)]];
 let' t3 ← list.replicate 128 (0 : int);
 let' t4 ← [(0 : nat), (0 : nat), (0 : nat), (0 : nat)];
 let' ret ← ⋆;
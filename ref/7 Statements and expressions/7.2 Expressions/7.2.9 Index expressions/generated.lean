@@ -21,9 +21,9 @@ let' ret ← t1;
 return (ret)
 
 
-definition test.bar : sem (string) :=
+definition test.bar : sem ((slice u8)) :=
 let' «n$1» ← (10 : nat);
-let' t4 ← ["a", "b"];
+let' t4 ← [[(97 : nat)], [(98 : nat)]];
 let' t5 ← «n$1»;
 let' t6 ← list.length t4;
 let' t7 ← t5 <ᵇ t6;
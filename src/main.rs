@@ -66,8 +66,22 @@ fn main() {
         _ => panic!("Expected .rs/.toml/crate name as single cmdline argument"),
     };
 
+    fn read_config(config_path: &path::Path) -> toml::Value {
+        let mut config = String::new();
+        let mut config_file = File::open(config_path).expect("error opening crate config");
+        config_file.read_to_string(&mut config).unwrap();
+        config.parse().unwrap()
+    }
+
     let (crate_name, base, rustc_args, config) = if input.ends_with(".rs") {
-        ("test".to_string(), path::PathBuf::from(&input).parent().unwrap().to_owned(), input, toml::Value::Table(toml::Table::new()))
+        let base = path::PathBuf::from(&input).parent().unwrap().to_owned();
+        // optional config next to the file, e.g. for reference cases
+        let config = if base.join("config.toml").exists() {
+            read_config(&base.join("config.toml"))
+        } else {
+            toml::Value::Table(toml::Table::new())
+        };
+        ("test".to_string(), base, input, config)
     } else {
         let (crate_name, config_path) = if input.ends_with(".toml") {
             ("test".to_string(), path::PathBuf::from(input))
@@ -76,10 +90,7 @@ fn main() {
             (input, config_path)
         };
         // load TOML config from 'thys/$crate/config.toml'
-        let mut config = String::new();
-        let mut config_file = File::open(&config_path).expect("error opening crate config");
-        config_file.read_to_string(&mut config).unwrap();
-        let config: toml::Value = config.parse().unwrap();
+        let config = read_config(&config_path);
         let mut rustc_args = config.lookup("rustc_args").expect("missing config item 'rustc_args'").as_str().unwrap().to_string();
         if rustc_args.starts_with("$RUST_SRC_PATH") {
             rustc_args = rustc_args.replace("$RUST_SRC_PATH", get_rust_src_path().expect("Please run 'rustup component add rust-src' in the electrolysis directory").to_str().unwrap())
@@ -88,7 +99,10 @@ fn main() {
     };
 
     // parse rustc options
-    let rustc_args = iter::once("rustc").chain(rustc_args.split(" ")).map(|s| s.to_string());
+    // make `#[cfg(spec)]` ghost functions visible, see `trans::ghost`
+    let spec_cfg = config.lookup("spec_cfg").and_then(toml::Value::as_bool).unwrap_or(false);
+    let spec_cfg = if spec_cfg { vec!["--cfg", "spec"] } else { vec![] };
+    let rustc_args = iter::once("rustc").chain(rustc_args.split(" ")).chain(spec_cfg).map(|s| s.to_string());
     let rustc_matches = rustc_driver::handle_options(&rustc_args.collect_vec()).expect("error parsing rustc args");
     let (mut options, cfg) = session::config::build_session_options_and_crate_config(&rustc_matches);
     options.crate_name = Some(crate_name);
@@ -207,6 +221,11 @@ fn transpile_crate(state: &driver::CompileState, config: &toml::Value, base: &pa
     let crate_name = state.crate_name.unwrap();
 
     let mut trans = CrateTranspiler::new(tcx, config);
+    let bitvec_ints = trans.config.bitvec_ints;
+    let heap_model = trans.config.heap_model;
+    let layout_model = trans.config.layout_model;
+    let monad_import = trans.config.monad.import.clone();
+    let cost_header = trans.config.cost.header();
     println!("Transpiling...");
 
     let targets = config.lookup("targets").map(|targets| {
@@ -217,22 +236,59 @@ fn transpile_crate(state: &driver::CompileState, config: &toml::Value, base: &pa
     let mut id_collector = IdCollector { tcx: tcx, ids: vec![] };
     intravisit::walk_crate(&mut id_collector, state.hir_crate.unwrap());
     let id_set = id_collector.ids.iter().cloned().collect::<HashSet<_>>();
-    for def_id in id_collector.ids {
-        let name = name_def_id(tcx, def_id);
-        if targets.iter().all(|targets| targets.is_match(&*name)) {
-            trans.transpile(def_id, &id_set);
-        }
+    trans.collect_spec_fns(&id_collector.ids);
+    let roots = id_collector.ids.iter().cloned().filter(|&def_id| {
+        targets.iter().all(|targets| targets.is_match(&*name_def_id(tcx, def_id)))
+    }).collect_vec();
+    // ghost functions are translated as dependencies of the specs calling them, but any of them may call
+    // trait methods dynamically
+    let dyn_roots = roots.iter().chain(trans.spec_fns.values().flat_map(|def_ids| def_ids)).cloned().collect::<HashSet<_>>();
+    trans.collect_dyn_methods(&id_collector.ids, &dyn_roots.into_iter().collect_vec());
+    for def_id in roots {
+        trans.transpile(def_id, &id_set);
     }
 
+    trans.transpile_globals();
     let (trans_results, trans::krate::Deps { mut crate_deps, graph, .. }) = trans.destruct();
 
+    // write out the target's pointer width, on which `usize.bits` in core/pre.lean is based
+    let target = &tcx.sess.target.target;
+    if crate_name == "core" {
+        let mut f = try!(File::create(base.join("target.lean")));
+        try!(write!(f, "-- generated for target {}
+
+namespace core.target
+definition triple := \"{}\"
+definition pointer_width [reducible] : ℕ := {}
+definition little_endian [reducible] : bool := {}
+end core.target
+", target.llvm_target, target.llvm_target, target.target_pointer_width,
+            if target.target_endian == "little" {"tt"} else {"ff"}));
+    }
+
     // write out theory header, importing dependencies and the pre file, if existent
 
     if crate_name != "core" {
         crate_deps.insert("core".to_string()); // always include prelude
     }
+    let mut int_reprs = crate_deps.iter().map(|c| {
+        (c.clone(), if trans::krate::config_bitvec_ints(c) { "bitvec" } else { "nat" })
+    }).collect_vec();
+    int_reprs.sort();
     let mut crate_deps = crate_deps.into_iter().map(|c| format!("{}.generated", c)).collect_vec();
     crate_deps.sort();
+    if bitvec_ints {
+        crate_deps.insert(0, "bv".to_string());
+    }
+    if heap_model {
+        crate_deps.insert(0, "heap".to_string());
+    }
+    if layout_model {
+        crate_deps.insert(0, "layout".to_string());
+    }
+    if let Some(import) = monad_import {
+        crate_deps.insert(0, import);
+    }
     let has_pre = base.join("pre.lean").exists();
     if has_pre {
         crate_deps.insert(0, format!("{}.pre", crate_name));
@@ -258,6 +314,20 @@ open [notation] unit
         try!(write!(f, "open {}\n", crate_name));
     }
     try!(write!(f, "\n"));
+    if crate_name != "core" {
+        // proofs about overflow only hold for a single target
+        try!(write!(f, "-- translated for target {}\nexample : core.target.pointer_width = {} := rfl\n\n",
+                    target.llvm_target, target.target_pointer_width));
+    }
+    // calls between crates with different integer representations are rejected, so check the dependencies
+    // were translated with the representation they were read with
+    try!(write!(f, "definition {}.int_repr [reducible] : string := \"{}\"\n",
+                crate_name, if bitvec_ints { "bitvec" } else { "nat" }));
+    for (dep, repr) in int_reprs {
+        try!(write!(f, "example : {}.int_repr = \"{}\" := rfl\n", dep, repr));
+    }
+    try!(write!(f, "\n"));
+    try!(write!(f, "{}", cost_header));
 
     // condensate sets of cyclic dependencies into graph nodes
     let condensed = condensation(graph, /* make_acyclic */ true);
@@ -295,12 +365,13 @@ open [notation] unit
             // cyclic dependencies, oh my
             ref component => {
                 let succeeded = component.iter().filter_map(|def_id| trans_results.get(def_id).and_then(|trans| trans.as_ref().ok())).collect_vec();
-                if succeeded.len() == component.len() {
-                    if succeeded.iter().all(|trans| trans.as_ref().unwrap().starts_with("inductive")) {
-                        // hackishly turn ["inductive A...", "inductive B..."] into "inductive A... with B..."
-                        try!(write!(f, "inductive {}\n\n", succeeded.iter().map(|trans| trans.as_ref().unwrap().trim_left_matches("inductive")).join("\n\nwith")));
-                        continue;
+                // recursive types are emitted as a whole by one member of their group, see `TypeGroup`
+                if succeeded.len() == component.len() &&
+                    succeeded.iter().all(|trans| trans.as_ref().map_or(true, |trans| trans.starts_with("inductive"))) {
+                    for trans in succeeded.into_iter().filter_map(|trans| trans.as_ref()) {
+                        try!(write!(f, "{}\n\n", trans));
                     }
+                    continue;
                 }
                 failed.insert(idx);
                 try!(write!(f, "/- unimplemented: circular dependencies: {}\n\n", component.iter().map(|&def_id| {
@@ -0,0 +1,115 @@
+use std::iter;
+
+use itertools::Itertools;
+
+use rustc::hir;
+use rustc::middle::const_val::ConstVal;
+use rustc::ty::{self, Ty};
+use rustc_const_eval::{eval_const_expr_partial, EvalHint};
+
+use util::*;
+use trans::item::{ItemTranspiler, mk_tuple};
+use trans::TransResult;
+
+impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
+    pub fn transpile_constval(&self, val: &ConstVal) -> TransResult {
+        Ok(match *val {
+            ConstVal::Bool(b) => (if b {"tt"} else {"ff"}).to_string(),
+            ConstVal::Integral(i) if self.config.bitvec_ints => match i.int_type().unwrap() {
+                ::syntax::attr::IntType::SignedInt(ty) =>
+                    format!("(bv.of_int {}.bits ({}))", ty, i.to_u64_unchecked() as i64),
+                ::syntax::attr::IntType::UnsignedInt(ty) =>
+                    format!("(bv.of_nat {}.bits {})", ty, i.to_u64_unchecked()),
+            },
+            ConstVal::Integral(i) => match i.int_type().unwrap() {
+                ::syntax::attr::IntType::SignedInt(_) =>
+                    format!("({} : int)", i.to_u64_unchecked() as i64),
+                ::syntax::attr::IntType::UnsignedInt(_) =>
+                    format!("({} : nat)", i.to_u64_unchecked()),
+            },
+            ConstVal::Char(c) =>
+                format!("({} : char32)", c as u32),
+            // UTF-8 bytes, see `is_str` in core/pre.lean
+            ConstVal::Str(ref s) => self.transpile_bytes(s.as_bytes()),
+            ConstVal::ByteStr(ref bytes) => self.transpile_bytes(bytes),
+            // there is no Lean type for floats to begin with, see `transpile_ty`
+            ConstVal::Float(_) => throw!("unimplemented: float literal"),
+            _ => throw!("unimplemented: literal | {:?}", val),
+        })
+    }
+
+    fn transpile_bytes(&self, bytes: &[u8]) -> String {
+        format!("[{}]", bytes.iter().map(|b| {
+            if self.config.bitvec_ints { format!("(bv.of_nat u8.bits {})", b) } else { format!("({} : nat)", b) }
+        }).join(", "))
+    }
+
+    /// List literal, or a reference to a separate definition in `tables` for large arrays
+    fn mk_const_list(&self, ty: Ty<'tcx>, elems: Vec<String>, tables: &mut Vec<String>) -> TransResult {
+        let list = format!("[{}]", elems.chunks(16).map(|chunk| chunk.join(", ")).join(",\n "));
+        if elems.len() <= self.config.const_table_threshold {
+            return Ok(list)
+        }
+        let elem_ty = match ty.sty {
+            ty::TypeVariants::TyArray(ty, _) | ty::TypeVariants::TySlice(ty) => ty,
+            _ => unreachable!(),
+        };
+        let name = format!("{}.table_{}", self.name(), tables.len());
+        tables.push(format!("definition {} : list {} :=\n{}", name, self.transpile_ty(elem_ty)?, list));
+        Ok(name)
+    }
+
+    /// Evaluates the aggregate structure of a constant expression, delegating to rustc for the leaves
+    fn eval_const_expr(&self, expr: &hir::Expr, tables: &mut Vec<String>) -> TransResult {
+        let ty = self.tcx.node_id_to_type(expr.id);
+        if ty.is_fp() {
+            throw!("unimplemented: float constant")
+        }
+        Ok(match expr.node {
+            hir::ExprAddrOf(hir::Mutability::MutImmutable, ref inner) =>
+                self.eval_const_expr(inner, tables)?,
+            hir::ExprTup(ref elems) =>
+                mk_tuple(elems.iter().map(|elem| self.eval_const_expr(elem, tables)).try()?),
+            hir::ExprArray(ref elems) => {
+                let elems = elems.iter().map(|elem| self.eval_const_expr(elem, tables)).try()?.collect_vec();
+                self.mk_const_list(ty, elems, tables)?
+            }
+            hir::ExprRepeat(ref elem, _) => match ty.sty {
+                ty::TypeVariants::TyArray(_, n) =>
+                    format!("(list.replicate {} {})", n, self.eval_const_expr(elem, tables)?),
+                _ => unreachable!(),
+            },
+            hir::ExprStruct(_, ref fields, None) => match ty.sty {
+                ty::TypeVariants::TyAdt(adt_def, _) if adt_def.adt_kind() == ty::AdtKind::Struct => {
+                    let vals = adt_def.struct_variant().fields.iter().map(|field| -> TransResult {
+                        let expr = &fields.iter().find(|f| f.name.node == field.name).unwrap().expr;
+                        let val = self.eval_const_expr(expr, tables)?;
+                        Ok(match self.nested_field(adt_def.did, field.unsubst_ty()) {
+                            Some(aux) => format!("({}.wrap {})", aux, val),
+                            None => val,
+                        })
+                    }).try()?;
+                    format!("({})", (format!("{}.mk", self.name_def_id(adt_def.did)), vals).join(" "))
+                }
+                _ => throw!("unimplemented: constant of type |{:?}", ty),
+            },
+            _ => match eval_const_expr_partial(self.tcx, expr, EvalHint::ExprTypeChecked, None) {
+                Ok(val) => self.transpile_constval(&val)?,
+                Err(err) => throw!("unimplemented: constant evaluation |{:?}", err),
+            },
+        })
+    }
+
+    /// `const X: T = e` ~> `definition X : sem T := return e'` with `e'` evaluated at translation time
+    pub fn transpile_evaluated_static(&self, name: &str, ty: &str) -> TransResult {
+        let expr = match self.tcx.map.expect_item(self.node_id()).node {
+            hir::Item_::ItemStatic(_, _, ref expr) | hir::Item_::ItemConst(_, ref expr) => expr,
+            _ => unreachable!(),
+        };
+        let mut tables = Vec::new();
+        let val = self.eval_const_expr(expr, &mut tables)?;
+        Ok(tables.into_iter()
+           .chain(iter::once(format!("definition {} : {} {} :=\n{} ({})", name, self.monad().ty, ty, self.monad().ret, val)))
+           .join("\n\n"))
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use regex::Regex;
+use toml;
+
+use rustc::mir::StatementKind;
+
+/// Costs charged by the generated code via `sem.incr`, see the `[cost]` table in the config. The generated code
+/// refers to them by the names defined in the header, e.g. `sem.incr crate.cost.block`.
+#[derive(Clone)]
+pub struct Costs {
+    // namespace of the definitions in the header, e.g. `crate.cost`
+    prefix: String,
+    // per call, unless overridden by `callees`
+    pub call: u64,
+    // per executed basic block
+    pub block: u64,
+    // per loop iteration
+    pub iteration: u64,
+    // per MIR statement kind, e.g. `assign`
+    pub statements: BTreeMap<String, u64>,
+    // per callee path, e.g. an amortized cost for `collections.vec.«Vec<T>».push`
+    pub callees: BTreeMap<String, u64>,
+}
+
+impl Default for Costs {
+    fn default() -> Costs {
+        Costs { prefix: String::new(), call: 1, block: 0, iteration: 0, statements: BTreeMap::new(), callees: BTreeMap::new() }
+    }
+}
+
+fn as_cost(key: &str, val: &toml::Value) -> u64 {
+    match val.as_integer() {
+        Some(n) if n >= 0 => n as u64,
+        _ => panic!("cost '{}' must be a non-negative integer", key),
+    }
+}
+
+impl Costs {
+    /// `base` with the entries of `table` overridden
+    fn parse(table: &toml::Value, base: &Costs, prefix: String) -> Costs {
+        let mut costs = Costs { prefix: prefix, ..base.clone() };
+        for (key, field) in vec![("call", &mut costs.call), ("block", &mut costs.block), ("iteration", &mut costs.iteration)] {
+            if let Some(val) = table.lookup(key) {
+                *field = as_cost(key, val);
+            }
+        }
+        for (key, map) in vec![("statements", &mut costs.statements), ("callees", &mut costs.callees)] {
+            if let Some(entries) = table.lookup(key) {
+                for (name, val) in entries.as_table().unwrap_or_else(|| panic!("'cost.{}' must be a table", key)) {
+                    map.insert(name.clone(), as_cost(name, val));
+                }
+            }
+        }
+        costs
+    }
+
+    fn constant(&self, name: &str) -> String {
+        format!("{}.{}", self.prefix, name)
+    }
+
+    /// `n₁ * c₁ + ...` over the named scalar costs that are not zero
+    fn sum(&self, counts: &BTreeMap<&str, u64>) -> Option<String> {
+        let entries = self.entries();
+        let terms = counts.iter().filter(|&(name, _)| {
+            entries.iter().any(|&(entry, cost)| entry == *name && cost > 0)
+        }).map(|(name, &n)| {
+            if n == 1 { self.constant(name) } else { format!("{} * {}", n, self.constant(name)) }
+        }).collect_vec();
+        if terms.is_empty() { None } else { Some(terms.join(" + ")) }
+    }
+
+    /// Cost of a basic block executing the given statements
+    pub fn block(&self, statements: &[&StatementKind]) -> Option<String> {
+        let mut counts = BTreeMap::new();
+        counts.insert("block", 1);
+        for kind in statements {
+            let key = match **kind {
+                StatementKind::Assign(..) => "assign",
+                StatementKind::SetDiscriminant { .. } => "set_discriminant",
+                StatementKind::StorageLive(_) => "storage_live",
+                StatementKind::StorageDead(_) => "storage_dead",
+                StatementKind::Nop => "nop",
+            };
+            *counts.entry(key).or_insert(0) += 1;
+        }
+        self.sum(&counts)
+    }
+
+    pub fn iteration(&self) -> Option<String> {
+        self.sum(&vec![("iteration", 1)].into_iter().collect())
+    }
+
+    /// Cost of calling the item at `path` (an unknown callee if `None`)
+    pub fn callee(&self, path: Option<&str>) -> Option<String> {
+        match path.and_then(|path| self.callees.get(path).map(|&cost| (path, cost))) {
+            Some((_, 0)) => None,
+            Some((path, _)) => Some(self.constant(&format!("callee.{}", path))),
+            None => self.sum(&vec![("call", 1)].into_iter().collect()),
+        }
+    }
+
+    /// Scalar costs by name, in the order of the header
+    fn entries(&self) -> Vec<(&str, u64)> {
+        let scalars = vec![("call", self.call), ("block", self.block), ("iteration", self.iteration)];
+        scalars.into_iter().chain(self.statements.iter().map(|(kind, &cost)| (&kind[..], cost))).collect()
+    }
+
+    /// Definitions of the scalar costs and callee overrides
+    fn definitions(&self) -> String {
+        let scalars = self.entries().into_iter().map(|(name, cost)| (self.constant(name), cost));
+        let callees = self.callees.iter().map(|(path, &cost)| (self.constant(&format!("callee.{}", path)), cost));
+        scalars.chain(callees).map(|(name, cost)| {
+            format!("definition {} [reducible] : ℕ := {}\n", name, cost)
+        }).join("")
+    }
+}
+
+pub struct CostModel {
+    pub enabled: bool,
+    default: Costs,
+    // `[cost.items."<item path regex>"]`, overriding the defaults for matching items
+    items: Vec<(Regex, Costs)>,
+}
+
+impl CostModel {
+    pub fn new(config: &toml::Value, crate_name: &str) -> CostModel {
+        let table = match config.lookup("cost") {
+            Some(table) => table,
+            None => return CostModel { enabled: false, default: Costs::default(), items: vec![] },
+        };
+        let default = Costs::parse(table, &Costs::default(), format!("{}.cost", crate_name));
+        let items = match table.lookup("items").map(|items| items.as_table().expect("'cost.items' must be a table")) {
+            Some(items) => items.iter().enumerate().map(|(i, (path, overrides))| {
+                let prefix = format!("{}.item_{}", default.prefix, i);
+                (Regex::new(&format!("^({})$", path)).unwrap(), Costs::parse(overrides, &default, prefix))
+            }).collect(),
+            None => vec![],
+        };
+        CostModel { enabled: true, default: default, items: items }
+    }
+
+    /// Costs for the item at `path`, with the first matching override
+    pub fn for_item(&self, path: &str) -> &Costs {
+        self.items.iter().find(|&&(ref regex, _)| regex.is_match(path)).map_or(&self.default, |&(_, ref costs)| costs)
+    }
+
+    /// Definitions of the chosen costs, including those of the item overrides
+    pub fn header(&self) -> String {
+        if !self.enabled {
+            return String::new()
+        }
+        let mut header = "-- cost model\n".to_string() + &self.default.definitions();
+        for &(ref regex, ref costs) in &self.items {
+            header += &format!("-- items matching {}\n{}", regex.as_str(), costs.definitions());
+        }
+        header + "\n"
+    }
+}
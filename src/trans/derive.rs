@@ -0,0 +1,238 @@
+use itertools::Itertools;
+
+use rustc::hir::def::CtorKind;
+use rustc::hir::def_id::DefId;
+use rustc::traits::Reveal;
+use rustc::ty::{self, Ty};
+use rustc::ty::subst::Substs;
+
+use util::*;
+use trans::item::{ItemTranspiler, LeanTyParam};
+use trans::krate;
+use trans::TransResult;
+
+/// `if h0 : x0 = y0 then ... decidable.inl _ else decidable.inr _` for the fields of a single constructor
+fn decide_fields(ty: &str, n: usize, k: usize) -> String {
+    if k == n {
+        return if n == 0 {
+            "decidable.inl rfl".to_string()
+        } else {
+            format!("decidable.inl (by rewrite [{}])", (0..n).map(|i| format!("h{}", i)).join(", "))
+        }
+    }
+    format!("if h{k} : x{k} = y{k} then {}\nelse decidable.inr (λ h, {ty}.no_confusion h (λ {}, absurd e{k} h{k}))",
+            decide_fields(ty, n, k + 1), (0..n).map(|i| format!("e{}", i)).join(" "), ty=ty, k=k)
+}
+
+/// `decidable_eq` by case distinction on the constructors (name and arity) of `ty`
+fn decidable_eq_instance(ty: &str, ctors: &[(String, usize)]) -> String {
+    let pat = |ctor: &str, n: usize, var: &str| if n == 0 { ctor.to_string() } else {
+        format!("({})", (ctor, (0..n).map(|i| format!("{}{}", var, i))).join(" "))
+    };
+    let cases = ctors.iter().enumerate().flat_map(|(i, &(ref ci, ni))| {
+        ctors.iter().enumerate().map(move |(j, &(ref cj, nj))| {
+            format!("| {} {} := {}", pat(ci, ni, "x"), pat(cj, nj, "y"), if i == j {
+                decide_fields(ty, ni, 0)
+            } else {
+                format!("decidable.inr (λ h, {}.no_confusion h)", ty)
+            })
+        }).collect_vec()
+    }).join("\n");
+    format!("definition {ty}.decidable_eq [instance] : decidable_eq {ty}\n{}", cases, ty=ty)
+}
+
+impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
+    pub fn is_derived(&self, impl_did: DefId) -> bool {
+        self.tcx.has_attr(impl_did, "automatically_derived")
+    }
+
+    /// Derived `Debug` impls are dropped along with their methods, see `fmt.Debug` in core/pre.lean
+    pub fn is_derived_debug(&self, impl_did: DefId) -> bool {
+        self.is_derived(impl_did) && self.tcx.impl_trait_ref(impl_did).map_or(false, |trait_ref| {
+            krate::name_def_id(self.tcx, trait_ref.def_id) == "core.fmt.Debug"
+        })
+    }
+
+    fn derives(&self, adt_did: DefId, trait_did: DefId) -> bool {
+        let mut derived = false;
+        self.tcx.lookup_trait_def(trait_did).for_each_relevant_impl(self.tcx, self.tcx.item_type(adt_did), |impl_did| {
+            derived |= self.is_derived(impl_did);
+        });
+        derived
+    }
+
+    /// Whether the derived impl of `trait_did` for `ty` is the identity (`Clone`) or Lean equality (`PartialEq`):
+    /// true for primitive types and for structs and enums deriving the trait over such fields
+    fn is_canonical(&self, ty: Ty<'tcx>, trait_did: DefId, visiting: &mut Vec<Ty<'tcx>>) -> bool {
+        match ty.sty {
+            ty::TypeVariants::TyBool | ty::TypeVariants::TyChar |
+            ty::TypeVariants::TyInt(_) | ty::TypeVariants::TyUint(_) => true,
+            ty::TypeVariants::TyRef(_, ty::TypeAndMut { ty, .. }) | ty::TypeVariants::TyBox(ty) |
+            ty::TypeVariants::TyArray(ty, _) => self.is_canonical(ty, trait_did, visiting),
+            ty::TypeVariants::TyTuple(tys) => tys.iter().all(|&ty| self.is_canonical(ty, trait_did, visiting)),
+            ty::TypeVariants::TyAdt(adt_def, substs) => {
+                if visiting.contains(&ty) || self.type_group(adt_def.did).is_some() ||
+                    !self.tcx.item_generics(adt_def.did).types.is_empty() {
+                    return false
+                }
+                visiting.push(ty);
+                let res = self.derives(adt_def.did, trait_did) &&
+                    adt_def.all_fields().all(|f| self.is_canonical(f.ty(self.tcx, substs), trait_did, visiting));
+                visiting.pop();
+                res
+            }
+            _ => false,
+        }
+    }
+
+    /// Emitted along with the type itself, where the instances of its field types are already available
+    fn transpile_decidable_eq(&self, adt_def: ty::AdtDef<'tcx>) -> String {
+        let name = krate::name_def_id(self.tcx, adt_def.did);
+        if adt_def.adt_kind() == ty::AdtKind::Struct {
+            return decidable_eq_instance(&name, &[(name.clone() + ".mk", adt_def.struct_variant().fields.len())])
+        }
+        let mut instances = vec![];
+        let ctors = adt_def.variants.iter().map(|variant| {
+            let ctor = krate::name_def_id(self.tcx, variant.did);
+            if variant.ctor_kind == CtorKind::Fictive {
+                let struct_name = format!("{}.struct", ctor);
+                instances.push(decidable_eq_instance(&struct_name, &[(struct_name.clone() + ".mk", variant.fields.len())]));
+                (ctor, 1)
+            } else {
+                (ctor, variant.fields.len())
+            }
+        }).collect_vec();
+        instances.push(decidable_eq_instance(&name, &ctors));
+        instances.join("\n\n")
+    }
+
+    /// Instances to be emitted with the definition of a type: `decidable_eq` for types whose derived `PartialEq`
+    /// is Lean equality, on which the derived `eq` and `ne` of the type and of types containing it rely
+    pub fn transpile_derived_instances(&self, adt_def: ty::AdtDef<'tcx>) -> Option<String> {
+        let eq_trait = match self.tcx.lang_items.eq_trait() {
+            Some(def_id) => def_id,
+            None => return None,
+        };
+        if self.derives(adt_def.did, eq_trait) && self.is_canonical(self.tcx.item_type(adt_def.did), eq_trait, &mut vec![]) {
+            Some(self.transpile_decidable_eq(adt_def))
+        } else {
+            None
+        }
+    }
+
+    /// `core::hash::Hash::hash` of a value of type `ty` into a `hasher`, with the instances given explicitly
+    fn hash_fn(&self, method_did: DefId, hasher: Ty<'tcx>, ty: Ty<'tcx>) -> TransResult {
+        let substs = self.tcx.mk_substs_trait(ty, &[hasher]);
+        self.tcx.infer_ctxt(None, Some(ty::ParameterEnvironment::for_item(self.tcx, self.node_id())), Reveal::All).enter(|infcx| {
+            let ty_params = self.transpile_ty_params_with_substs(method_did, self.def_id, substs, false)?.into_iter().map(|p| Ok(match p {
+                LeanTyParam::RustTyParam(name) | LeanTyParam::AssocTy(name) => name,
+                LeanTyParam::TraitRef(_, _, trait_ref) => self.infer_trait_impl(trait_ref, &infcx)?.to_string(self)?,
+            })).try()?;
+            Ok(format!("(@{})", (self.name_def_id(method_did), ty_params).join(" ")))
+        })
+    }
+
+    /// Derived `hash`: the discriminant (if there is more than one variant), then all fields in order
+    fn transpile_derived_hash(&self, head: &str, ty: &str, method_did: DefId, adt_def: ty::AdtDef<'tcx>,
+                              substs: &'tcx Substs<'tcx>) -> TransResult {
+        let sig = self.tcx.item_type(self.def_id).fn_sig().skip_binder().clone();
+        let hasher = self.unwrap_mut_ref(sig.inputs[1]);
+        let state_ty = self.transpile_ty(hasher)?;
+        let discr_ty = self.tcx.types.u64;
+        let arms = adt_def.variants.iter().map(|variant| -> TransResult {
+            let vars = (0..variant.fields.len()).map(|i| format!("x{}", i)).collect_vec();
+            let pat = if adt_def.adt_kind() == ty::AdtKind::Struct {
+                (krate::name_def_id(self.tcx, adt_def.did) + ".mk", &vars).join(" ")
+            } else if variant.ctor_kind == CtorKind::Fictive {
+                let ctor = krate::name_def_id(self.tcx, variant.did);
+                format!("{} ({})", ctor, (ctor.clone() + ".struct.mk", &vars).join(" "))
+            } else {
+                (krate::name_def_id(self.tcx, variant.did), &vars).join(" ")
+            };
+            let mut hashed = vec![];
+            if adt_def.variants.len() > 1 {
+                let discr = variant.disr_val.to_u64_unchecked();
+                let discr = if self.config.bitvec_ints { format!("(bv.of_nat u64.bits {})", discr) } else { format!("({} : nat)", discr) };
+                hashed.push((self.hash_fn(method_did, hasher, discr_ty)?, discr));
+            }
+            for (field, var) in variant.fields.iter().zip(&vars) {
+                hashed.push((self.hash_fn(method_did, hasher, field.ty(self.tcx, substs))?, var.clone()));
+            }
+            let body = hashed.iter().enumerate().map(|(i, &(ref hash, ref val))| {
+                let state = if i == 0 { "state".to_string() } else { format!("r{}.2", i - 1) };
+                format!("{} r{} ← {} {} {};\n", self.monad().bind, i, hash, val, state)
+            }).join("");
+            let state = if hashed.is_empty() { "state".to_string() } else { format!("r{}.2", hashed.len() - 1) };
+            Ok(format!("| {} :=\n{}{} (⋆, {})", pat, body, self.monad().ret, state))
+        }).try()?.join("\n");
+        Ok(format!("definition {} (self : {}) (state : {}) : {} (unit × {}) :=\nmatch self with\n{}\nend",
+                   head, ty, state_ty, self.monad().ty, state_ty, arms))
+    }
+
+    /// Short structural definitions of methods of derived impls instead of their expanded MIR
+    pub fn transpile_derived_method(&self, impl_did: DefId) -> TransResult<Option<String>> {
+        if !self.is_derived(impl_did) {
+            return Ok(None)
+        }
+        let trait_ref = match self.tcx.impl_trait_ref(impl_did) {
+            Some(trait_ref) => trait_ref,
+            None => return Ok(None),
+        };
+        let self_ty = trait_ref.self_ty();
+        let (adt_def, substs) = match self_ty.sty {
+            ty::TypeVariants::TyAdt(adt_def, substs) => (adt_def, substs),
+            _ => return Ok(None),
+        };
+        let name = self.name();
+        let ty_params = self.transpile_ty_params(self.def_id)?.iter().map(LeanTyParam::to_string).collect_vec();
+        let head = (&name, ty_params.iter()).join(" ");
+        let lemma_head = (name.clone() + ".spec", ty_params.iter()).join(" ");
+        let ty = self.transpile_ty(self_ty)?;
+        let trait_name = krate::name_def_id(self.tcx, trait_ref.def_id);
+        let method = self.tcx.item_name(self.def_id).as_str();
+        Ok(Some(match (&trait_name[..], &*method) {
+            ("core.cmp.PartialEq", "eq") | ("core.cmp.PartialEq", "ne")
+                if self.is_canonical(self_ty, trait_ref.def_id, &mut vec![]) => {
+                let prop = if &*method == "eq" { "self = other" } else { "self ≠ other" };
+                format!("definition {} (self other : {}) : {m} bool :=\n{ret} (bool.of_Prop ({}))\n\n\
+                         lemma {} (self other : {}) : {returns} (bool.of_Prop ({})) ({} self other) :=\nrfl",
+                        head, ty, prop, lemma_head, ty, prop, name, m=self.monad().ty, ret=self.monad().ret, returns=self.monad().returns)
+            }
+            ("core.clone.Clone", "clone") if self.is_canonical(self_ty, trait_ref.def_id, &mut vec![]) =>
+                format!("definition {} (self : {}) : {m} {} :=\n{ret} self\n\n\
+                         lemma {} (self : {}) : {returns} self ({} self) :=\nrfl",
+                        head, ty, ty, lemma_head, ty, name, m=self.monad().ty, ret=self.monad().ret, returns=self.monad().returns),
+            ("core.hash.Hash", "hash") if self.type_group(adt_def.did).is_none() => {
+                let method_did = self.tcx.associated_items(trait_ref.def_id).find(|item| item.name.as_str() == method)
+                    .ok_or_else(|| format!("error: no method `{}` in `{}`", method, trait_name))?.def_id;
+                self.transpile_derived_hash(&head, &ty, method_did, adt_def, substs)?
+            }
+            // C-like enums are ordered by their discriminants
+            ("core.cmp.PartialOrd", method) if adt_def.adt_kind() == ty::AdtKind::Enum &&
+                adt_def.variants.iter().all(|v| v.ctor_kind == CtorKind::Const) => {
+                let enum_name = self.name_def_id(adt_def.did);
+                let (a, b) = (format!("{}.discr self", enum_name), format!("{}.discr other", enum_name));
+                let body = match method {
+                    "partial_cmp" => {
+                        let res = format!("core.option.Option.Some (if {a} < {b} then core.cmp.Ordering.Less\n\
+                                           else if {a} = {b} then core.cmp.Ordering.Equal else core.cmp.Ordering.Greater)",
+                                          a=a, b=b);
+                        return Ok(Some(format!(
+                            "definition {} (self other : {}) : {m} (core.option.Option core.cmp.Ordering) :=\n{ret} ({res})\n\n\
+                             lemma {} (self other : {}) : {returns} ({res}) ({} self other) :=\nrfl",
+                            head, ty, lemma_head, ty, name, res=res, m=self.monad().ty, ret=self.monad().ret, returns=self.monad().returns)))
+                    }
+                    "lt" => format!("{} < {}", a, b),
+                    "le" => format!("{} ≤ {}", a, b),
+                    "gt" => format!("{} > {}", a, b),
+                    "ge" => format!("{} ≥ {}", a, b),
+                    _ => return Ok(None),
+                };
+                format!("definition {} (self other : {}) : {m} bool :=\n{ret} (bool.of_Prop ({}))\n\n\
+                         lemma {} (self other : {}) : {returns} (bool.of_Prop ({})) ({} self other) :=\nrfl",
+                        head, ty, body, lemma_head, ty, body, name, m=self.monad().ty, ret=self.monad().ret, returns=self.monad().returns)
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
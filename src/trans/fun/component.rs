@@ -89,7 +89,7 @@ impl<'a> Component<'a> {
         for &bb in blocks {
             for stmt in &trans.mir[bb].statements {
                 match stmt.kind {
-                    StatementKind::Assign(ref lv, Rvalue::Ref(_, BorrowKind::Mut, ref dest)) => {
+                    StatementKind::Assign(ref lv, Rvalue::Ref(_, kind, ref dest)) if trans.is_mut_borrow(kind, dest) => {
                         if let Some(llv) = lvalue(lv, &mut uses) {
                             defs.insert(llv);
                         }
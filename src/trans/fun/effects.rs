@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use rustc::hir;
+use rustc::hir::def_id::{DefId, CRATE_DEF_INDEX};
+use rustc::mir::*;
+use rustc::mir::visit::{LvalueContext, Visitor};
+use rustc::ty::{self, Ty, TyCtxt};
+
+use item_path;
+use trans::fun::is_raw_ptr;
+use trans::fun::intrinsics::{self, Intrinsic};
+use trans::item::ItemTranspiler;
+use trans::krate::{self, CrateTranspiler};
+
+/// Implicit state a function takes as additional last parameters and returns as additional last
+/// components of its result, just like `&mut` parameters
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Effects {
+    /// the record of all mutable statics (`global_state = true`)
+    pub globals: bool,
+    /// the heap of heap.lean (`heap_model = true`)
+    pub heap: bool,
+}
+
+impl Effects {
+    fn union(self, other: Effects) -> Effects {
+        Effects { globals: self.globals || other.globals, heap: self.heap || other.heap }
+    }
+
+    /// Names of the state variables, in parameter order
+    pub fn vars(&self) -> Vec<String> {
+        let mut vars = vec![];
+        if self.globals { vars.push("globals__".to_string()) }
+        if self.heap { vars.push("heap__".to_string()) }
+        vars
+    }
+
+    /// Lean types of the state variables, in parameter order
+    pub fn tys(&self, tcx: TyCtxt) -> Vec<String> {
+        let mut tys = vec![];
+        if self.globals { tys.push(globals_ty(tcx)) }
+        if self.heap { tys.push("heap".to_string()) }
+        tys
+    }
+}
+
+/// `mycrate.globals`
+pub fn globals_ty(tcx: TyCtxt) -> String {
+    format!("{}.globals", krate::name_def_id(tcx, DefId::local(CRATE_DEF_INDEX)))
+}
+
+/// `mycrate::foo::COUNTER` ~> `«foo::COUNTER»`, a single identifier that is as unique as the Rust path
+pub fn globals_field(tcx: TyCtxt, def_id: DefId) -> String {
+    let mut buffer = Vec::new();
+    item_path::push_item_path(tcx, &mut buffer, def_id);
+    format!("«{}»", buffer[1..].join("::").replace("«", "").replace("»", ""))
+}
+
+/// Raw pointers and `Rc`/`Arc` handles point into the heap
+pub fn uses_heap<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>, ty: Ty<'tcx>) -> bool {
+    ty.walk().any(|ty| is_raw_ptr(ty) || match ty.sty {
+        ty::TypeVariants::TyAdt(adt_def, _) => krate::is_rc(tcx, adt_def.did),
+        _ => false,
+    })
+}
+
+pub fn is_static_mut(tcx: TyCtxt, def_id: DefId) -> bool {
+    match tcx.map.get_if_local(def_id) {
+        Some(hir::map::NodeItem(&hir::Item { node: hir::Item_::ItemStatic(_, hir::Mutability::MutMutable, _), .. })) => true,
+        _ => false,
+    }
+}
+
+/// Whether the item has a body translated by `transpile_fn`
+pub fn has_mir(tcx: TyCtxt, def_id: DefId) -> bool {
+    use rustc::hir::map::Node;
+
+    match tcx.map.get_if_local(def_id) {
+        Some(Node::NodeItem(&hir::Item { node: hir::Item_::ItemFn(..), .. })) |
+        Some(Node::NodeImplItem(&hir::ImplItem { node: hir::ImplItemKind::Method(..), .. })) |
+        Some(Node::NodeTraitItem(&hir::TraitItem { node: hir::TraitItem_::MethodTraitItem(_, Some(_)), .. })) |
+        Some(Node::NodeExpr(&hir::Expr { node: hir::ExprClosure(..), .. })) => true,
+        _ => false,
+    }
+}
+
+struct StaticMutFinder<'a, 'tcx: 'a> {
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    found: bool,
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for StaticMutFinder<'a, 'tcx> {
+    fn visit_lvalue(&mut self, lvalue: &Lvalue<'tcx>, context: LvalueContext<'tcx>, location: Location) {
+        if let Lvalue::Static(def_id) = *lvalue {
+            self.found |= is_static_mut(self.tcx, def_id);
+        }
+        self.super_lvalue(lvalue, context, location);
+    }
+}
+
+/// Tarjan's algorithm over the call graph, see `CrateTranspiler::effects`
+#[derive(Default)]
+struct SccSearch {
+    index: HashMap<DefId, usize>,
+    lowlink: HashMap<DefId, usize>,
+    stack: Vec<DefId>,
+    // own effects joined with those of callees outside the component
+    acc: HashMap<DefId, Effects>,
+}
+
+impl<'a, 'tcx> CrateTranspiler<'a, 'tcx> {
+    /// The implicit state used by a function: mutable statics it accesses and the heap if it mentions
+    /// raw pointers or `Rc`s, including those of its callees. Other crates' functions are only judged by their
+    /// signature.
+    pub fn effects(&self, def_id: DefId) -> Effects {
+        if !self.config.global_state && !self.config.heap_model {
+            return Effects::default()
+        }
+        if let Some(&effects) = self.effects_cache.borrow().get(&def_id) {
+            return effects
+        }
+        // (mutually) recursive functions share their effects, so solve whole strongly connected components
+        self.effects_scc(def_id, &mut SccSearch::default());
+        self.effects_cache.borrow()[&def_id]
+    }
+
+    fn effects_scc(&self, def_id: DefId, search: &mut SccSearch) {
+        let idx = search.index.len();
+        search.index.insert(def_id, idx);
+        search.lowlink.insert(def_id, idx);
+        search.stack.push(def_id);
+        let mut acc = self.own_effects(def_id);
+        for callee in self.callees(def_id) {
+            if let Some(&effects) = self.effects_cache.borrow().get(&callee) {
+                acc = acc.union(effects);
+                continue
+            }
+            match search.index.get(&callee).cloned() {
+                None => {
+                    self.effects_scc(callee, search);
+                    match self.effects_cache.borrow().get(&callee) {
+                        // callee's component is complete
+                        Some(&effects) => acc = acc.union(effects),
+                        None => {
+                            let low = ::std::cmp::min(search.lowlink[&def_id], search.lowlink[&callee]);
+                            search.lowlink.insert(def_id, low);
+                        }
+                    }
+                }
+                // on the stack, i.e. in the current component
+                Some(callee_idx) => {
+                    let low = ::std::cmp::min(search.lowlink[&def_id], callee_idx);
+                    search.lowlink.insert(def_id, low);
+                }
+            }
+        }
+        search.acc.insert(def_id, acc);
+
+        if search.lowlink[&def_id] == idx {
+            let pos = search.stack.iter().rposition(|&did| did == def_id).unwrap();
+            let component = search.stack.split_off(pos);
+            let effects = component.iter().fold(Effects::default(), |effects, did| effects.union(search.acc[did]));
+            let mut cache = self.effects_cache.borrow_mut();
+            for did in component {
+                cache.insert(did, effects);
+            }
+        }
+    }
+
+    /// Effects of the function itself, disregarding its callees
+    fn own_effects(&self, def_id: DefId) -> Effects {
+        let mut effects = Effects::default();
+        if let Some(Intrinsic::Heap(_)) = intrinsics::lookup(&krate::name_def_id(self.tcx, def_id)) {
+            effects.heap = self.config.heap_model;
+            return effects
+        }
+        effects.heap = self.config.heap_model && uses_heap(self.tcx, self.tcx.item_type(def_id));
+        if !has_mir(self.tcx, def_id) {
+            return effects
+        }
+        let mir = self.tcx.item_mir(def_id);
+        if self.config.heap_model {
+            effects.heap |= mir.local_decls.iter().any(|decl| uses_heap(self.tcx, decl.ty));
+        }
+        if self.config.global_state {
+            let mut finder = StaticMutFinder { tcx: self.tcx, found: false };
+            finder.visit_mir(&mir);
+            effects.globals = finder.found;
+        }
+        effects
+    }
+
+    /// Statically known callees of a function with MIR, with trait methods resolved to their impl where
+    /// possible, just like calls are by `FnTranspiler::get_call_target`
+    fn callees(&self, def_id: DefId) -> Vec<DefId> {
+        if !has_mir(self.tcx, def_id) || intrinsics::lookup(&krate::name_def_id(self.tcx, def_id)).is_some() {
+            return vec![]
+        }
+        let caller = ItemTranspiler { sup: self, def_id: def_id, substs: None };
+        self.tcx.item_mir(def_id).basic_blocks().iter().filter_map(|data| match data.terminator().kind {
+            TerminatorKind::Call {
+                func: Operand::Constant(Constant { literal: Literal::Item { def_id: callee, substs }, .. }), ..
+            } => Some(match self.tcx.trait_of_item(callee) {
+                Some(_) => caller.static_call_target(callee, substs).unwrap_or(callee),
+                None => callee,
+            }),
+            _ => None,
+        }).collect()
+    }
+}
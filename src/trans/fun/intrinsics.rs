@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use rustc::mir::*;
+use rustc::hir::def_id::DefId;
+use rustc::ty::{self, Ty, TyCtxt};
+
+use util::*;
+use trans::fun::FnTranspiler;
+use trans::krate;
+use trans::TransResult;
+
+/// How calls to an intrinsic (or a function wrapping one) are translated
+#[derive(Clone, Copy, Debug)]
+pub enum Intrinsic {
+    /// Lean model in `core.intrinsics` (`bv` with `int_repr = "bitvec"`) that breaks parametricity and
+    /// has to be invoked with the bit count and signedness of its first argument
+    Num(&'static str),
+    /// `size_of::<T>()`, computed from rustc's layout of the concrete `T`
+    SizeOf,
+    /// `align_of::<T>()`, computed from rustc's layout of the concrete `T`
+    AlignOf,
+    /// branch prediction hint, returns its argument
+    Hint,
+    /// undefined behavior if the argument is false, which we treat as failure
+    Assume,
+    /// diverges without unwinding
+    Abort,
+    /// primitive of the heap model in `heap.lean`, taking and returning the heap (`heap_model = true`)
+    Heap(&'static str),
+    /// `decode (encode x)` with the byte encodings of layout.lean (`layout_model = true`)
+    Transmute,
+}
+
+lazy_static! {
+    static ref INTRINSICS: HashMap<String, Intrinsic> = {
+        use self::Intrinsic::*;
+
+        let mut m = HashMap::new();
+        for &name in &["add_with_overflow", "sub_with_overflow", "mul_with_overflow",
+                       "overflowing_add", "overflowing_sub", "overflowing_mul",
+                       "ctpop", "ctlz", "cttz", "bswap"] {
+            m.insert(format!("core.intrinsics.{}", name), Num(name));
+        }
+        for name in &["core.intrinsics.size_of", "core.mem.size_of"] {
+            m.insert(name.to_string(), SizeOf);
+        }
+        for name in &["core.intrinsics.min_align_of", "core.mem.align_of", "core.mem.min_align_of"] {
+            m.insert(name.to_string(), AlignOf);
+        }
+        for name in &["core.intrinsics.likely", "core.intrinsics.unlikely"] {
+            m.insert(name.to_string(), Hint);
+        }
+        m.insert("core.intrinsics.assume".to_string(), Assume);
+        m.insert("core.intrinsics.abort".to_string(), Abort);
+        m.insert("core.intrinsics.transmute".to_string(), Transmute);
+        for &(name, model) in &[("alloc.heap.allocate", "allocate"), ("alloc.heap.reallocate", "reallocate"),
+                                ("alloc.heap.deallocate", "deallocate"),
+                                ("core.ptr.null", "null"), ("core.ptr.null_mut", "null"),
+                                ("core.«*const T».is_null", "is_null"), ("core.«*mut T».is_null", "is_null"),
+                                ("core.intrinsics.offset", "offset"),
+                                ("core.«*const T».offset", "offset"), ("core.«*mut T».offset", "offset"),
+                                ("core.ptr.read", "ptr_read"), ("core.ptr.write", "ptr_write"),
+                                ("core.intrinsics.copy", "copy"),
+                                ("core.intrinsics.copy_nonoverlapping", "copy_nonoverlapping")] {
+            m.insert(name.to_string(), Heap(model));
+        }
+        m
+    };
+}
+
+pub fn lookup(name: &str) -> Option<Intrinsic> {
+    INTRINSICS.get(name).cloned()
+}
+
+/// `rotate_left`/`rotate_right` of the integer types: no intrinsics in `core`, but their shift-based definitions are
+/// a pain to reason about. Identified by their inherent impl, whose path is not stable across rustc versions.
+fn int_rotation(tcx: TyCtxt, def_id: DefId) -> Option<Intrinsic> {
+    let name = tcx.item_name(def_id).to_string();
+    if name != "rotate_left" && name != "rotate_right" {
+        return None
+    }
+    match tcx.impl_of_method(def_id) {
+        Some(impl_def_id) if tcx.trait_id_of_impl(impl_def_id).is_none() && tcx.item_type(impl_def_id).is_integral() =>
+            Some(Intrinsic::Num(if name == "rotate_left" { "rotate_left" } else { "rotate_right" })),
+        _ => None,
+    }
+}
+
+/// Methods of `Rc<T>`/`Arc<T>` and their `Weak<T>` implemented by `handle` in alloc/pre.lean (`heap_model = true`),
+/// whose bodies are replaced by a call of the model
+pub fn rc_model(tcx: TyCtxt, def_id: DefId) -> Option<&'static str> {
+    let adt_def = match tcx.impl_of_method(def_id).map(|impl_def_id| &tcx.item_type(impl_def_id).sty) {
+        Some(&ty::TypeVariants::TyAdt(adt_def, _)) if krate::is_rc(tcx, adt_def.did) => adt_def,
+        _ => return None,
+    };
+    let weak = &*tcx.item_name(adt_def.did).as_str() == "Weak";
+    Some(match (weak, &*tcx.item_name(def_id).as_str()) {
+        (false, "new") => "new",
+        (false, "strong_count") => "strong_count",
+        (false, "weak_count") => "weak_count",
+        (false, "downgrade") => "downgrade",
+        (false, "get_mut") => "get_mut",
+        (false, "make_mut") => "make_mut",
+        (false, "clone") => "clone",
+        (false, "deref") => "deref",
+        (true, "upgrade") => "upgrade",
+        (true, "clone") => "clone_weak",
+        _ => return None,
+    })
+}
+
+impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
+    /// Looks up the call target in the intrinsic registry
+    pub fn get_intrinsic(&self, func: &Operand<'tcx>) -> Option<Intrinsic> {
+        match *func {
+            // don't use `self.name_def_id`, which would add a dependency on the (foreign) item
+            Operand::Constant(Constant { literal: Literal::Item { def_id, .. }, .. }) =>
+                match lookup(&krate::name_def_id(self.tcx, def_id)).or_else(|| int_rotation(self.tcx, def_id)) {
+                    Some(Intrinsic::Heap(_)) if !self.config.heap_model => None,
+                    Some(Intrinsic::Transmute) if !self.config.layout_model => None,
+                    intrinsic => intrinsic,
+                },
+            _ => None,
+        }
+    }
+
+    /// Size or alignment of a concrete type in bytes
+    fn layout_bytes(&self, intrinsic: Intrinsic, ty: Ty<'tcx>) -> TransResult<u64> {
+        self.with_layout(ty, |layout, dl| Ok(match intrinsic {
+            Intrinsic::SizeOf => layout.size(dl).bytes(),
+            Intrinsic::AlignOf => layout.align(dl).abi(),
+            _ => unreachable!(),
+        }))
+    }
+
+    /// Translates a call of a registered intrinsic to a `sem` computation
+    pub fn transpile_intrinsic_call(&self, intrinsic: Intrinsic, func: &Operand<'tcx>, args: &[Operand<'tcx>],
+                                    sargs: Vec<String>) -> TransResult {
+        let substs = match *func {
+            Operand::Constant(Constant { literal: Literal::Item { substs, .. }, .. }) => self.mono_substs(substs),
+            _ => unreachable!(),
+        };
+        Ok(match intrinsic {
+            Intrinsic::Num(name) => {
+                let ty = args[0].ty(self.mir, self.tcx);
+                let prefix = if self.config.bitvec_ints { "bv." } else { "core.intrinsics." };
+                let suffix = if ty.is_signed() { "_signed" } else { "" };
+                (format!("{}{}{} {}", prefix, name, suffix, self.transpile_bits(ty)?), sargs).join(" ")
+            }
+            Intrinsic::SizeOf | Intrinsic::AlignOf => {
+                let bytes = self.layout_bytes(intrinsic, substs[0].as_type().unwrap())?;
+                if self.config.bitvec_ints {
+                    format!("{} (bv.of_nat usize.bits {})", self.monad().ret, bytes)
+                } else {
+                    format!("{} ({} : nat)", self.monad().ret, bytes)
+                }
+            }
+            Intrinsic::Hint => format!("{} {}", self.monad().ret, sargs[0]),
+            Intrinsic::Assume => format!("{} ({} = tt) ({} ⋆)", self.monad().guard, sargs[0], self.monad().ret),
+            // never returns, see `FnTranspiler::transpile_basic_block`
+            Intrinsic::Abort => unreachable!(),
+            Intrinsic::Heap(name) =>
+                (format!("heap.{}", name), sargs.into_iter().chain(Some("heap__".to_string()))).join(" "),
+            Intrinsic::Transmute => {
+                let src = self.transpile_codec(substs[0].as_type().unwrap())?;
+                let dst = self.transpile_codec(substs[1].as_type().unwrap())?;
+                format!("{} ({} ({} {}))", self.monad().lift, dst.decode, src.encode, sargs[0])
+            }
+        })
+    }
+}
@@ -1,37 +1,43 @@
 mod component;
+mod effects;
+mod intrinsics;
+mod moves;
+mod spec;
 
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::iter;
 use std::ops::Deref;
+use std::rc::Rc;
 
 use itertools::Itertools;
-use regex::Regex;
 
 use rustc::hir;
 use rustc::hir::def::CtorKind;
-use rustc::hir::def_id::DefId;
+use rustc::hir::def_id::{DefId, CRATE_DEF_INDEX};
 use rustc::mir::*;
 use rustc::middle::const_val::ConstVal;
 use rustc::traits;
 use rustc::ty::{self, Ty};
 use rustc::ty::subst::Substs;
+use rustc_const_math::{ConstMathErr, Op};
 use rustc_data_structures::indexed_vec::Idx;
 use syntax::ast;
+use syntax::codemap::Span;
 
 use self::component::Component;
+pub use self::effects::{Effects, globals_field, globals_ty, has_mir, is_static_mut};
+use self::intrinsics::Intrinsic;
 use util::*;
 use trans::item::{self, LeanTyParam, mk_tuple, detuplize};
 use trans::krate;
+use trans::cost::Costs;
+use trans::layout;
+use trans::monad::{self, Monad};
 use trans::TransResult;
 
-lazy_static! {
-    // intrinsics that break parametricity and have to be invoked with bit count and signedness
-    static ref NUM_INTRINSICS: Regex =
-        Regex::new(r"^core.intrinsics.(overflowing_.*|.*_with_overflow)$").unwrap();
-}
-
 /// `get_tuple_elem('x', 1, 3)` ~> `'x.1.2'`
-fn get_tuple_elem<S : AsRef<str>>(value: S, idx: usize, len: usize) -> String {
+pub fn get_tuple_elem<S : AsRef<str>>(value: S, idx: usize, len: usize) -> String {
     let fsts = iter::repeat(".1").take(len - idx - 1);
     let snd = if idx == 0 { None } else { Some(".2") };
     iter::once(value.as_ref()).chain(fsts).chain(snd).join("")
@@ -45,13 +51,43 @@ fn set_tuple_elem<S : AsRef<str>>(tuple: S, value: S, idx: usize, len: usize) ->
 }
 
 /// `&&T` ~> `T`
-fn unwrap_refs<'tcx>(ty: Ty<'tcx>) -> Ty<'tcx> {
+pub fn unwrap_refs<'tcx>(ty: Ty<'tcx>) -> Ty<'tcx> {
     match ty.sty {
         ty::TypeVariants::TyRef(_, ty::TypeAndMut { ty, mutbl: hir::Mutability::MutImmutable }) => unwrap_refs(ty),
         _ => ty
     }
 }
 
+/// `Shl` on `(i32, u64)` ~> `"sshl"`, following the naming scheme of `checked.*` in core/pre.lean
+fn arith_op_name<'tcx>(op: BinOp, to1: Ty<'tcx>, to2: Ty<'tcx>) -> String {
+    let name = match op {
+        BinOp::Add => "add",
+        BinOp::Sub => "sub",
+        BinOp::Mul => "mul",
+        BinOp::Div => "div",
+        BinOp::Rem => "rem",
+        BinOp::Shl => "shl",
+        BinOp::Shr => "shr",
+        _ => unreachable!(),
+    };
+    let name = if to1.is_signed() { format!("s{}", name) } else { name.to_string() };
+    match op {
+        // shift amount may be of a different type
+        BinOp::Shl | BinOp::Shr => if to2.is_signed() { name + "s" } else { name },
+        _ => {
+            assert!(to1 == to2);
+            name
+        }
+    }
+}
+
+pub fn is_raw_ptr<'tcx>(ty: Ty<'tcx>) -> bool {
+    match ty.sty {
+        ty::TypeVariants::TyRawPtr(_) => true,
+        _ => false,
+    }
+}
+
 fn lvalue_of_operand<'a, 'tcx>(op: &'a Operand<'tcx>) -> &'a Lvalue<'tcx> {
     match *op {
         Operand::Consume(ref lv) => lv,
@@ -59,6 +95,17 @@ fn lvalue_of_operand<'a, 'tcx>(op: &'a Operand<'tcx>) -> &'a Lvalue<'tcx> {
     }
 }
 
+/// Overflow checks of `+`, `<<` etc., which are only enabled with `overflow_checks`.
+/// Division overflow (`MIN / -1`) is always checked.
+fn is_overflow_check(msg: &AssertMessage) -> bool {
+    match *msg {
+        AssertMessage::Math(ConstMathErr::Overflow(Op::Div))
+        | AssertMessage::Math(ConstMathErr::Overflow(Op::Rem)) => false,
+        AssertMessage::Math(ConstMathErr::Overflow(_)) => true,
+        _ => false,
+    }
+}
+
 trait AsLocal {
     fn as_local(&self) -> Option<Local>;
 }
@@ -82,9 +129,11 @@ impl MaybeValue {
     fn total<T: ToString>(val: T) -> MaybeValue { MaybeValue { val: val.to_string(), total: true } }
     fn partial<T: ToString>(val: T) -> MaybeValue { MaybeValue { val: val.to_string(), total: false } }
 
-    fn to_partial(self) -> String {
+    fn to_partial(self, trans: &FnTranspiler) -> String {
         if self.total {
-            format!("return ({})", self.val)
+            format!("{} ({})", trans.monad().ret, self.val)
+        } else if trans.total {
+            monad::PARTIAL.to_string()
         } else { self.val }
     }
     fn to_total(self) -> String {
@@ -94,60 +143,62 @@ impl MaybeValue {
         self.val
     }
 
-    fn try_and_then<F: FnOnce(String) -> TransResult<MaybeValue>>(self, depth: u32, f: F) -> TransResult<MaybeValue> {
+    fn try_and_then<F: FnOnce(String) -> TransResult<MaybeValue>>(self, trans: &FnTranspiler, depth: u32, f: F) -> TransResult<MaybeValue> {
         if self.total {
             f(self.val)
+        } else if trans.total {
+            throw!("unimplemented: partial operation in pure function")
         } else {
             let tmp = format!("«$tmp{}»", depth);
             let new = f(tmp.clone())?;
             Ok(MaybeValue::partial(format!(
-                "do {} ← {};\n{}", tmp, self.val, new.to_partial())))
+                "{} {} ← {};\n{}", trans.monad().bind, tmp, self.val, new.to_partial(trans))))
         }
     }
 
-    fn and_then<F: FnOnce(String) -> MaybeValue>(self, depth: u32, f: F) -> MaybeValue {
-        self.try_and_then(depth, |var| Ok(f(var))).unwrap()
+    fn and_then<F: FnOnce(String) -> MaybeValue>(self, trans: &FnTranspiler, depth: u32, f: F) -> MaybeValue {
+        self.try_and_then(trans, depth, |var| Ok(f(var))).unwrap()
     }
 
-    fn try_map<F: FnOnce(String) -> TransResult>(self, depth: u32, f: F) -> TransResult {
-        Ok(self.try_and_then(depth, |var| Ok(MaybeValue::partial(f(var)?)))?.val)
+    fn try_map<F: FnOnce(String) -> TransResult>(self, trans: &FnTranspiler, depth: u32, f: F) -> TransResult {
+        Ok(self.try_and_then(trans, depth, |var| Ok(MaybeValue::partial(f(var)?)))?.val)
     }
 
-    fn map<F: FnOnce(String) -> String>(self, depth: u32, f: F) -> String {
-        self.try_map(depth, |var| Ok(f(var))).unwrap()
+    fn map<F: FnOnce(String) -> String>(self, trans: &FnTranspiler, depth: u32, f: F) -> String {
+        self.try_map(trans, depth, |var| Ok(f(var))).unwrap()
     }
 
-    fn try_and_then_multi<It, F>(depth: u32, vals: It, f: F) -> TransResult<MaybeValue>
+    fn try_and_then_multi<It, F>(trans: &FnTranspiler, depth: u32, vals: It, f: F) -> TransResult<MaybeValue>
         where It: Iterator<Item=MaybeValue>,
               F: FnOnce(Vec<String>) -> TransResult<MaybeValue>,
     {
-        fn rec<It, F>(depth: u32, mut vals: It, mut vars: Vec<String>, f: F) -> TransResult<MaybeValue>
+        fn rec<It, F>(trans: &FnTranspiler, depth: u32, mut vals: It, mut vars: Vec<String>, f: F) -> TransResult<MaybeValue>
             where It: Iterator<Item=MaybeValue>,
             F: FnOnce(Vec<String>) -> TransResult<MaybeValue>,
         {
             match vals.next() {
                 None => f(vars),
-                Some(val) => val.try_and_then(depth, |var| {
+                Some(val) => val.try_and_then(trans, depth, |var| {
                     vars.push(var);
-                    rec(depth + 1, vals, vars, f)
+                    rec(trans, depth + 1, vals, vars, f)
                 })
             }
         }
-        rec(depth, vals, Vec::new(), f)
+        rec(trans, depth, vals, Vec::new(), f)
     }
 
-    fn and_then_multi<It, F>(depth: u32, vals: It, f: F) -> MaybeValue
+    fn and_then_multi<It, F>(trans: &FnTranspiler, depth: u32, vals: It, f: F) -> MaybeValue
         where It: Iterator<Item=MaybeValue>,
               F: FnOnce(Vec<String>) -> MaybeValue
     {
-        MaybeValue::try_and_then_multi(depth, vals, |var| Ok(f(var))).unwrap()
+        MaybeValue::try_and_then_multi(trans, depth, vals, |var| Ok(f(var))).unwrap()
     }
 
-    fn try_map_multi<It, F>(depth: u32, vals: It, f: F) -> TransResult
+    fn try_map_multi<It, F>(trans: &FnTranspiler, depth: u32, vals: It, f: F) -> TransResult
         where It: Iterator<Item=MaybeValue>,
               F: FnOnce(Vec<String>) -> TransResult,
     {
-        Ok(MaybeValue::try_and_then_multi(depth, vals, |vars| Ok(MaybeValue::partial(f(vars)?)))?.val)
+        Ok(MaybeValue::try_and_then_multi(trans, depth, vals, |vars| Ok(MaybeValue::partial(f(vars)?)))?.val)
     }
 }
 
@@ -158,6 +209,12 @@ pub struct FnTranspiler<'a, 'tcx: 'a> {
     // helper definitions to be prepended to the translation
     prelude: Vec<String>,
     refs: HashMap<Local, Lvalue<'tcx>>,
+    // implicit state passed in and out, see `CrateTranspiler::effects`
+    state: Effects,
+    // move analysis for `transpile_drop`, computed on first use
+    moves: Rc<RefCell<Option<moves::Moves<'a, 'tcx>>>>,
+    // translate to plain values instead of the configured monad, see `new_total`
+    total: bool,
 }
 
 impl<'a, 'tcx> Deref for FnTranspiler<'a, 'tcx> {
@@ -175,9 +232,21 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
             mir: mir,
             prelude: Default::default(),
             refs: Default::default(),
+            state: Effects::default(),
+            moves: Default::default(),
+            total: false,
         }
     }
 
+    /// Translation of a ghost function as a plain value, for which any partial operation yields `monad::PARTIAL`
+    pub fn new_total(sup: &'a item::ItemTranspiler<'a, 'tcx>, mir: &'a Mir<'tcx>) -> FnTranspiler<'a, 'tcx> {
+        FnTranspiler { total: true, ..FnTranspiler::new(sup, mir) }
+    }
+
+    fn monad(&self) -> &Monad {
+        if self.total { monad::identity() } else { self.sup.monad() }
+    }
+
     fn local_name(&self, local: Local) -> String {
         let opt_name = self.mir.local_decls[local].name;
         match self.mir.local_kind(local) {
@@ -203,19 +272,39 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
         lv.as_local().and_then(|local| self.refs.get(&local).cloned())
     }
 
+    /// `mycrate::COUNTER` ~> `«COUNTER»` if it is part of the global state record
+    fn global_field(&self, lv: &Lvalue<'tcx>) -> Option<String> {
+        match *lv {
+            Lvalue::Static(def_id) if self.state.globals && is_static_mut(self.tcx, def_id) => {
+                self.add_dep(def_id);
+                Some(effects::globals_field(self.tcx, def_id))
+            }
+            _ => None,
+        }
+    }
+
     fn get_lvalue(&self, lv: &Lvalue<'tcx>) -> TransResult<MaybeValue> {
+        if let Some(field) = self.global_field(lv) {
+            return Ok(MaybeValue::total(format!("({}.{} globals__)", effects::globals_ty(self.tcx), field)))
+        }
         if let Some(name) = self.lvalue_name(lv) {
             return Ok(MaybeValue::total(name))
         }
 
         match *lv {
             Lvalue::Projection(box Projection { ref base, elem: ProjectionElem::Deref }) => {
-                if let Some(ref src) = self.deref_mut(base) {
+                if is_raw_ptr(self.lvalue_ty(base)) {
+                    // read from the heap
+                    Ok(self.get_lvalue(base)?.and_then(self, 0, |base| {
+                        MaybeValue::partial(format!("{} (heap.read heap__ {})", self.monad().lift, base))
+                    }))
+                } else if let Some(ref src) = self.deref_mut(base) {
                     // read through a &mut
-                    self.get_lvalue(base)?.try_and_then(0, |base| Ok(self.get_lvalue(src)?.and_then(1, |src| {
+                    self.get_lvalue(base)?.try_and_then(self, 0, |base| Ok(self.get_lvalue(src)?.and_then(self, 1, |src| {
                         MaybeValue::partial(format!("lens.get {} {}", base, src))
                     })))
                 } else {
+                    // `&T` and `Box<T>` are transparent
                     self.get_lvalue(base)
                 }
             }
@@ -229,24 +318,37 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
                         vars = vec![format!("({})", (self.name_def_id(var.did) + ".struct.mk", vars).join(" "))]
                     }
                     format!("| {} := {}\n", (self.name_def_id(var.did), vars).join(" "), if i == variant {
-                        format!("return «${}»", field.index())
-                    } else {"mzero".to_string()})
+                        match self.nested_field(adt_def.did, var.fields[field.index()].unsubst_ty()) {
+                            Some(aux) => format!("{} ({}.unwrap «${}»)", self.monad().ret, aux, field.index()),
+                            None => format!("{} «${}»", self.monad().ret, field.index()),
+                        }
+                    } else { self.monad().fail.clone() })
                 }).join(" ");
-                Ok(self.get_lvalue(base)?.and_then(0, |base| {
+                Ok(self.get_lvalue(base)?.and_then(self, 0, |base| {
                     MaybeValue::partial(format!("match {} with\n{}end\n", base, arms))
                 }))
             }
             Lvalue::Projection(box Projection { ref base, elem: ProjectionElem::Index(ref idx) }) =>
-                self.get_lvalue(base)?.try_and_then(0, |base| Ok(self.get_operand(idx)?.and_then(1, |idx| {
-                    MaybeValue::partial(format!("core.«[T] as core.slice.SliceExt».get_unchecked {} {}", base, idx))
+                self.get_lvalue(base)?.try_and_then(self, 0, |base| Ok(self.get_operand(idx)?.and_then(self, 1, |idx| {
+                    MaybeValue::partial(format!("core.«[T] as core.slice.SliceExt».get_unchecked {} {}", base, self.index_to_nat(idx)))
                 }))),
+            // union field ~> decoding a prefix of its bytes
+            Lvalue::Projection(box Projection { ref base, elem: ProjectionElem::Field(_, ref field_ty) })
+                if layout::is_union(self.lvalue_ty(base)) => {
+                let codec = self.transpile_codec(*field_ty)?;
+                let union = self.name_def_id(self.lvalue_ty(base).ty_adt_def().unwrap().did);
+                Ok(self.get_lvalue(base)?.and_then(self, 0, |base| {
+                    MaybeValue::partial(format!("{} ({} (layout.slice 0 {} ({}.bytes {})))", self.monad().lift,
+                                                codec.decode, codec.size, union, base))
+                }))
+            }
             // `x.0`, `x.f`
             Lvalue::Projection(box Projection { ref base, elem: ProjectionElem::Field(ref field, _) }) =>
-                self.get_lvalue(base)?.try_and_then(0, |sbase| Ok(MaybeValue::total(match unwrap_refs(self.lvalue_ty(base)).sty {
+                self.get_lvalue(base)?.try_and_then(self, 0, |sbase| Ok(MaybeValue::total(match unwrap_refs(self.lvalue_ty(base)).sty {
                     ty::TypeVariants::TyTuple(ref tys) =>
                         get_tuple_elem(sbase, field.index(), tys.len()),
                     ty::TypeVariants::TyAdt(ref adt_def, _) => {
-                        if adt_def.struct_variant().ctor_kind == CtorKind::Fn { // tuple struct
+                        if adt_def.struct_variant().ctor_kind == CtorKind::Fn && self.type_group(adt_def.did).is_none() { // tuple struct
                             format!("match {} with {}.mk {} := x{} end",
                                     sbase,
                                     self.name_def_id(adt_def.did),
@@ -275,6 +377,20 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
 
     fn update_struct(&self, ty: ty::Ty<'tcx>, field: Field, base: &str, val: &str) -> TransResult {
         let adt_def = ty.ty_adt_def().unwrap();
+        if self.type_group(adt_def.did).is_some() {
+            // not a Lean structure, see `TypeGroup`
+            let fields = &adt_def.struct_variant().fields;
+            let vars = (0..fields.len()).map(|i| format!("x{}", i)).collect_vec();
+            let val = match self.nested_field(adt_def.did, fields[field.index()].unsubst_ty()) {
+                Some(aux) => format!("({}.wrap {})", aux, val),
+                None => val.to_string(),
+            };
+            let ctor = self.name_def_id(adt_def.did) + ".mk";
+            return Ok(format!("match {} with {} := {} end", base, (&ctor, vars.iter()).join(" "),
+                              (&ctor, vars.iter().enumerate().map(|(i, x)| {
+                                  if i == field.index() { val.clone() } else { x.clone() }
+                              })).join(" ")))
+        }
         let field_name = self.mk_lean_name(&*adt_def.struct_variant().fields[field.index()].name.as_str());
         Ok(if adt_def.struct_variant().fields.len() > 1 {
             format!("(let' («$tmp» : {ty}) ← {}; ⦃ {ty}, {} := {}, «$tmp» ⦄)",
@@ -286,6 +402,10 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
     }
 
     fn set_lvalue(&self, depth: u32, lv: &Lvalue<'tcx>, val: &str) -> TransResult {
+        if let Some(field) = self.global_field(lv) {
+            return Ok(format!("let' globals__ ← ⦃ {}, {} := {}, globals__ ⦄;\n",
+                              effects::globals_ty(self.tcx), field, val))
+        }
         if let Some(name) = self.lvalue_name(lv) {
             return Ok(if name == val { // no-op
                 "".to_string()
@@ -297,19 +417,29 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
             Lvalue::Projection(box Projection { ref base, ref elem }) =>
                 self.get_lvalue(base)?.try_map(depth, |sbase| match *elem {
                     ProjectionElem::Deref => {
-                        if let Some(ref src) = self.deref_mut(base) {
-                            Ok(self.get_lvalue(src)?.map(1, |src| {
+                        if is_raw_ptr(self.lvalue_ty(base)) {
+                            Ok(format!("{} heap__ ← {} (heap.write heap__ {} {});\n", self.monad().bind, self.monad().lift, sbase, val))
+                        } else if let Some(ref src) = self.deref_mut(base) {
+                            Ok(self.get_lvalue(src)?.map(self, 1, |src| {
                                 // writing through a &mut
-                                format!("do {src} ← lens.set {lens} {src} {val};\n",
-                                        src=src, lens=sbase, val=val)
+                                format!("{bind} {src} ← lens.set {lens} {src} {val};\n",
+                                        bind=self.monad().bind, src=src, lens=sbase, val=val)
                             }))
                         } else {
+                            // `&T` and `Box<T>` are transparent
                             self.set_lvalue(depth + 1, base, val)
                         }
                     }
-                    ProjectionElem::Field(field, _) => {
+                    ProjectionElem::Field(field, field_ty) => {
                         let ty = unwrap_refs(self.lvalue_ty(base));
                         match ty.sty {
+                            ty::TypeVariants::TyAdt(adt_def, _) if adt_def.adt_kind() == ty::AdtKind::Union => {
+                                let codec = self.transpile_codec(field_ty)?;
+                                let union = self.name_def_id(adt_def.did);
+                                self.set_lvalue(depth + 1, base, &format!(
+                                    "({union}.mk (layout.overwrite 0 ({} {}) ({union}.bytes {})))",
+                                    codec.encode, val, sbase, union=union))
+                            }
                             ty::TypeVariants::TyAdt(_, _) =>
                                 self.set_lvalue(depth + 1, base, &self.update_struct(ty, field, &sbase, val)?),
                             ty::TypeVariants::TyClosure(def_id, ref substs) => {
@@ -322,8 +452,8 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
                         }
                     }
                     ProjectionElem::Index(ref index) => {
-                        self.get_operand(index)?.try_map(1, |index| {
-                            MaybeValue::partial(format!("sem.lift_opt (list.update {} {} {})", sbase, index, val)).try_map(2, |new| {
+                        self.get_operand(index)?.try_map(self, 1, |index| {
+                            MaybeValue::partial(format!("{} (list.update {} {} {})", self.monad().lift, sbase, self.index_to_nat(index), val)).try_map(self, 2, |new| {
                                 self.set_lvalue(depth + 1, base, &new)
                             })
                         })
@@ -334,22 +464,6 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
         }
     }
 
-    fn transpile_constval(&self, val: &ConstVal) -> TransResult {
-        Ok(match *val {
-            ConstVal::Bool(b) => (if b {"tt"} else {"ff"}).to_string(),
-            ConstVal::Integral(i) => match i.int_type().unwrap() {
-                ::syntax::attr::IntType::SignedInt(_) =>
-                    format!("({} : int)", i.to_u64_unchecked() as i64),
-                ::syntax::attr::IntType::UnsignedInt(_) =>
-                    format!("({} : nat)", i.to_u64_unchecked()),
-            },
-            ConstVal::Char(c) =>
-                format!("({} : char32)", c as u32),
-            ConstVal::Str(ref s) => format!("\"{}\"", s),
-            _ => throw!("unimplemented: literal | {:?}", val),
-        })
-    }
-
     fn get_constant(&self, c: &Constant<'tcx>) -> TransResult<MaybeValue> {
         Ok(match c.literal {
             Literal::Value { ref value } => MaybeValue::total(self.transpile_constval(value)?),
@@ -363,6 +477,10 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
                         _ => {}
                     }
                 }
+                if let Some((def_id, substs)) = self.resolve_instance(def_id, substs)? {
+                    return Ok(MaybeValue::total(self.mono_name(def_id, substs)))
+                }
+                let substs = self.mono_substs(substs);
                 self.tcx.infer_ctxt(None, Some(ty::ParameterEnvironment::for_item(self.tcx, self.node_id())), ::rustc::traits::Reveal::All).enter(|infcx| -> TransResult<MaybeValue> {
                     Ok(MaybeValue::total(
                         format!("(@{})", (self.name_def_id(def_id), self.transpile_ty_params_with_substs(def_id, def_id, substs, false)?.into_iter().map(|p| match p {
@@ -392,71 +510,102 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
             Rvalue::Use(ref op) => self.get_operand(op),
             Rvalue::UnaryOp(op, ref operand) => {
                 let toperand = operand.ty(self.mir, self.tcx);
-                self.get_operand(operand)?.try_and_then(0, |soperand| Ok(MaybeValue::total(format!("{} {}", match op {
+                let bv = self.num_prefix();
+                self.get_operand(operand)?.try_and_then(self, 0, |soperand| Ok(MaybeValue::total(format!("{} {}", match op {
                     UnOp::Not if toperand.is_bool() => "bool.bnot".to_string(),
-                    UnOp::Not => format!("{}bitnot {}.bits",
+                    UnOp::Not if self.config.bitvec_ints => "bitvec.not".to_string(),
+                    UnOp::Not => format!("{}bitnot {}",
                                          if toperand.is_signed() {"s"} else {""},
-                                         self.transpile_ty(toperand)?),
+                                         self.transpile_bits(toperand)?),
+                    UnOp::Neg if !self.config.overflow_checks =>
+                        format!("{}wrapping.neg {}", bv, self.transpile_bits(toperand)?),
                     UnOp::Neg =>
-                        return Ok(MaybeValue::partial(format!("checked.neg {}.bits {}",
-                                                              self.transpile_ty(toperand)?,
+                        return Ok(MaybeValue::partial(format!("{}checked.neg {} {}", bv,
+                                                              self.transpile_bits(toperand)?,
                                                               soperand))),
                     }
                 , soperand))))
             }
             Rvalue::BinaryOp(op, ref o1, ref o2) => {
-                self.get_operand(o1)?.try_and_then(0, |so1| self.get_operand(o2)?.try_and_then(1, |so2| {
+                self.get_operand(o1)?.try_and_then(self, 0, |so1| self.get_operand(o2)?.try_and_then(self, 1, |so2| {
                     let to1 = o1.ty(self.mir, self.tcx);
                     let to2 = o2.ty(self.mir, self.tcx);
-                    let checked_homogenous_binop = |name: &'static str| {
-                        assert!(to1 == to2);
-                        let name = if to1.is_signed() { format!("s{}", name) } else { name.to_string() };
-                        Ok(MaybeValue::partial(format!("checked.{} {}.bits {} {}", name, self.transpile_ty(to1)?, so1, so2)))
-                    };
-                    let checked_shift = |name: &'static str| {
-                        let name = if to1.is_signed() { format!("s{}", name) } else { name.to_string() };
-                        let name = if to2.is_signed() { name + "s" } else { name.to_string() };
-                        Ok(MaybeValue::partial(format!("checked.{} {}.bits {} {}", name, self.transpile_ty(to1)?, so1, so2)))
+                    let bv = self.num_prefix();
+                    let arith_binop = || {
+                        let name = arith_op_name(op, to1, to2);
+                        let bits = self.transpile_bits(to1)?;
+                        Ok(match op {
+                            // without overflow checks, everything but division wraps around
+                            BinOp::Div | BinOp::Rem => MaybeValue::partial(format!("{}checked.{} {} {} {}", bv, name, bits, so1, so2)),
+                            _ if !self.config.overflow_checks =>
+                                MaybeValue::total(format!("{}wrapping.{} {} {} {}", bv, name, bits, so1, so2)),
+                            _ => MaybeValue::partial(format!("{}checked.{} {} {} {}", bv, name, bits, so1, so2)),
+                        })
                     };
-                    let bitop = |name: &str, bool_name| {
+                    let bitop = |name: &str, bool_name, bv_name| {
                         assert!(to1 == to2);
                         Ok(if to1.is_bool() {
                             MaybeValue::total(format!("{} {} {}", bool_name, so1, so2))
+                        } else if self.config.bitvec_ints {
+                            MaybeValue::total(format!("{} {} {}", bv_name, so1, so2))
                         } else {
                             let name = if to1.is_signed() { format!("s{}", name) } else { name.to_string() };
-                            MaybeValue::total(format!("{} {}.bits {} {}", name, self.transpile_ty(to1)?, so1, so2))
+                            MaybeValue::total(format!("{} {} {} {}", name, self.transpile_bits(to1)?, so1, so2))
                         })
                     };
                     let infix_binop = |name| Ok(MaybeValue::total(format!("{} {} {}", so1, name, so2)));
+                    // bit vectors need to know how to interpret their bits
+                    let cmp_binop = |name, bv_name| if self.config.bitvec_ints && to1.is_integral() {
+                        Ok(MaybeValue::total(format!("bitvec.{}{} {} {}", if to1.is_signed() {"s"} else {"u"}, bv_name, so1, so2)))
+                    } else {
+                        infix_binop(name)
+                    };
                     match op {
-                        BinOp::Add => checked_homogenous_binop("add"),
-                        BinOp::Sub => checked_homogenous_binop("sub"),
-                        BinOp::Mul => checked_homogenous_binop("mul"),
-                        BinOp::Div => checked_homogenous_binop("div"),
-                        BinOp::Rem => checked_homogenous_binop("rem"),
-                        BinOp::Shl => checked_shift("shl"),
-                        BinOp::Shr => checked_shift("shr"),
-                        BinOp::BitOr => bitop("bitor", "bor"),
-                        BinOp::BitAnd => bitop("bitand", "band"),
-                        BinOp::BitXor => bitop("bitxor", "bxor"),
+                        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem
+                        | BinOp::Shl | BinOp::Shr => arith_binop(),
+                        BinOp::BitOr => bitop("bitor", "bor", "bitvec.or"),
+                        BinOp::BitAnd => bitop("bitand", "band", "bitvec.and"),
+                        BinOp::BitXor => bitop("bitxor", "bxor", "bitvec.xor"),
                         BinOp::Eq => infix_binop("=ᵇ"),
-                        BinOp::Lt => infix_binop("<ᵇ"),
-                        BinOp::Le => infix_binop("≤ᵇ"),
+                        BinOp::Lt => cmp_binop("<ᵇ", "lt"),
+                        BinOp::Le => cmp_binop("≤ᵇ", "le"),
                         BinOp::Ne => infix_binop("≠ᵇ"),
-                        BinOp::Ge => infix_binop("≥ᵇ"),
-                        BinOp::Gt => infix_binop(">ᵇ"),
+                        BinOp::Ge => cmp_binop("≥ᵇ", "ge"),
+                        BinOp::Gt => cmp_binop(">ᵇ", "gt"),
                     }
                 }))
             }
-            // checked operators used in `Assert`... but we've already checked
+            // checked operators used in `Assert`
             Rvalue::CheckedBinaryOp(op, ref o1, ref o2) => {
+                if self.config.panic_reasons || !self.config.overflow_checks {
+                    // compute the actual overflow flag, which is then checked by the `Assert` (if at all)
+                    let to1 = o1.ty(self.mir, self.tcx);
+                    let to2 = o2.ty(self.mir, self.tcx);
+                    let name = arith_op_name(op, to1, to2);
+                    return Ok(MaybeValue::partial(MaybeValue::try_map_multi(self, 0, vec![self.get_operand(o1)?, self.get_operand(o2)?].into_iter(), |ops| {
+                        Ok(format!("{}overflowing.{} {} {}", self.num_prefix(), name, self.transpile_bits(to1)?, ops.join(" ")))
+                    })?))
+                }
+                // ...but the checked operation has already panicked on overflow
                 let MaybeValue { val, total } = self.get_rvalue(&Rvalue::BinaryOp(op, o1.clone(), o2.clone()))?;
                 Ok(if total {
-                    MaybeValue::total(format!("({}, tt)", val))
+                    MaybeValue::total(format!("({}, ff)", val))
                 } else {
-                    MaybeValue::partial(format!("sem.map (λx, (x, tt)) ({})", val))
+                    MaybeValue::partial(format!("{} (λx, (x, ff)) ({})", self.monad().map, val))
                 })
             }
+            Rvalue::Cast(CastKind::Misc, ref op, dest_ty) if is_raw_ptr(op.ty(self.mir, self.tcx)) && is_raw_ptr(dest_ty) => {
+                let pointee = |ty: Ty<'tcx>| match ty.sty {
+                    ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ty, .. }) => ty,
+                    _ => unreachable!(),
+                };
+                if pointee(op.ty(self.mir, self.tcx)) == pointee(dest_ty) {
+                    self.get_operand(op)
+                } else {
+                    // may fail, see `ptr.cast`
+                    Ok(self.get_operand(op)?.and_then(self, 0, |op| MaybeValue::partial(format!("{} (ptr.cast {})", self.monad().lift, op))))
+                }
+            }
             Rvalue::Cast(CastKind::Misc, ref op, ref dest_ty) => {
                 let op_ty = op.ty(self.mir, self.tcx);
                 let trans_ty = |ty: ty::Ty<'tcx>| match ty.sty {
@@ -467,14 +616,17 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
                 };
                 let sop = trans_ty(op_ty)?;
                 let sdest = trans_ty(dest_ty)?;
-                self.get_operand(op)?.try_and_then(0, |operand| Ok(MaybeValue::partial(
+                let bv = self.num_prefix();
+                self.get_operand(op)?.try_and_then(self, 0, |operand| Ok(MaybeValue::partial(
                     if op_ty.is_integral() || op_ty.is_bool() || op_ty.is_char() {
-                        format!("({}_to_{} {}.bits {})",
-                                sop, sdest, self.transpile_ty(dest_ty)?,
+                        format!("({}{}_to_{} {} {})",
+                                bv, sop, sdest, self.transpile_bits(dest_ty)?,
                                 operand)
                     } else if let ty::TypeVariants::TyAdt(..) = op_ty.sty {
-                        format!("(signed_to_{} {}.bits ({}.discr {}))",
-                                sdest, self.transpile_ty(dest_ty)?,
+                        // `discr` is an `int` in either representation
+                        let conv = if self.config.bitvec_ints { "bv.int" } else { "signed" };
+                        format!("({}_to_{} {} ({}.discr {}))",
+                                conv, sdest, self.transpile_bits(dest_ty)?,
                                 self.name_def_id(op_ty.ty_to_def_id().unwrap()),
                                 operand)
                     } else {
@@ -489,21 +641,35 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
                 Ok(if ops.len() == 0 {
                     MaybeValue::total("⋆")
                 } else {
-                    MaybeValue::and_then_multi(0, ops.iter().map(|op| self.get_operand(op)).try()?, |ops| {
+                    MaybeValue::and_then_multi(self, 0, ops.iter().map(|op| self.get_operand(op)).try()?, |ops| {
                         MaybeValue::total(mk_tuple(ops))
                     })
                 })
             }
             Rvalue::Aggregate(AggregateKind::Array, ref ops) => {
-                Ok(MaybeValue::and_then_multi(0, ops.iter().map(|op| self.get_operand(op)).try()?, |ops| {
+                Ok(MaybeValue::and_then_multi(self, 0, ops.iter().map(|op| self.get_operand(op)).try()?, |ops| {
                     MaybeValue::total(format!("[{}]", ops.join(", ")))
                 }))
             }
+            // union literal ~> bytes of its single field
+            Rvalue::Aggregate(AggregateKind::Adt(ref adt_def, _, substs, Some(active_field)), ref ops) => {
+                let codec = self.transpile_codec(adt_def.struct_variant().fields[active_field].ty(self.tcx, substs))?;
+                let union = self.transpile_codec(self.tcx.item_type(adt_def.did))?;
+                let name = self.name_def_id(adt_def.did);
+                Ok(self.get_operand(&ops[0])?.and_then(self, 0, |op| {
+                    MaybeValue::total(format!("{}.mk (layout.place {} [(0, {} {})])", name, union.size, codec.encode, op))
+                }))
+            }
             Rvalue::Aggregate(AggregateKind::Adt(ref adt_def, variant_idx, _, _), ref ops) => {
                 self.add_dep(adt_def.did);
 
                 let variant = &adt_def.variants[variant_idx];
-                Ok(MaybeValue::and_then_multi(0, ops.iter().map(|op| self.get_operand(op)).try()?, |ops| {
+                let nested = variant.fields.iter().map(|f| self.nested_field(adt_def.did, f.unsubst_ty())).collect_vec();
+                Ok(MaybeValue::and_then_multi(self, 0, ops.iter().map(|op| self.get_operand(op)).try()?, |ops| {
+                    let ops = ops.into_iter().zip(&nested).map(|(op, nested)| match *nested {
+                        Some(ref aux) => format!("({}.wrap {})", aux, op),
+                        None => op,
+                    }).collect_vec();
                     let mut val = self.name_def_id(variant.did);
                     if variant.ctor_kind == CtorKind::Fictive {
                         match adt_def.adt_kind() {
@@ -518,17 +684,24 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
                     MaybeValue::total((val, ops).join(" "))
                 }))
             }
-            Rvalue::Aggregate(AggregateKind::Closure(def_id, _), ref ops) => {
+            Rvalue::Aggregate(AggregateKind::Closure(def_id, closure_substs), ref ops) => {
                 let upvars = ops.iter().map(lvalue_of_operand).collect_vec();
-                Ok(MaybeValue::and_then_multi(0, upvars.iter().map(|lv| self.get_lvalue(lv)).try()?, |upvars| {
-                    MaybeValue::total(format!("{}.mk {}", self.name_def_id(def_id),
+                let name = if self.config.monomorphize {
+                    self.mono_name(def_id, self.mono_substs(closure_substs.substs))
+                } else { self.name_def_id(def_id) };
+                Ok(MaybeValue::and_then_multi(self, 0, upvars.iter().map(|lv| self.get_lvalue(lv)).try()?, |upvars| {
+                    MaybeValue::total(format!("{}.mk {}", name,
                                               mk_tuple(upvars.into_iter())))
                 }))
             }
-            Rvalue::Len(ref lv) => Ok(self.get_lvalue(lv)?.and_then(0, |lv| {
-                MaybeValue::total(format!("list.length {}", lv))
+            Rvalue::Len(ref lv) => Ok(self.get_lvalue(lv)?.and_then(self, 0, |lv| {
+                MaybeValue::total(if self.config.bitvec_ints {
+                    format!("bv.of_nat usize.bits (list.length {})", lv)
+                } else {
+                    format!("list.length {}", lv)
+                })
             })),
-            Rvalue::Repeat(ref op, ref times) => Ok(self.get_operand(op)?.and_then(0, |op| {
+            Rvalue::Repeat(ref op, ref times) => Ok(self.get_operand(op)?.and_then(self, 0, |op| {
                 use rustc_const_math::ConstUsize::*;
                 let times = match times.value {
                     Us16(t) => t as u64,
@@ -542,6 +715,11 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
         }
     }
 
+    /// `&mut x`, or `&x` for interior mutable `x`
+    fn is_mut_borrow(&self, kind: BorrowKind, lv: &Lvalue<'tcx>) -> bool {
+        kind == BorrowKind::Mut || self.is_interior_mut(self.lvalue_ty(lv))
+    }
+
     /// Makes path of lenses and return eventual target
     fn mk_lenses(&self, lv: &'a Lvalue<'tcx>, lenses: &mut Vec<String>) -> TransResult<&'a Lvalue<'tcx>> {
         if lv.as_local().is_some() {
@@ -551,18 +729,22 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
         match *lv {
             Lvalue::Projection(box Projection { ref base, ref elem }) => {
                 match *elem {
-                    ProjectionElem::Deref =>
+                    ProjectionElem::Deref => {
+                        if is_raw_ptr(self.lvalue_ty(base)) {
+                            throw!("unimplemented: &mut into the heap |{:?}", lv)
+                        }
                         if self.deref_mut(base).is_some() {
                             return Ok(base)
-                        },
+                        }
+                    }
                     ProjectionElem::Field(field, _) => {
                         let ty = unwrap_refs(self.lvalue_ty(base));
                         match ty.sty {
                             ty::TypeVariants::TyAdt(ref adt_def, _) => match adt_def.adt_kind() {
                                 ty::AdtKind::Struct => {
                                     let field_name = adt_def.struct_variant().fields[field.index()].name;
-                                    lenses.push(format!("lens.mk (return ∘ {ty}.{field}) (λ (o : {applied_ty}) i, return {setter})",
-                                                        ty=self.name_def_id(adt_def.did), applied_ty=self.transpile_ty(ty)?, field=field_name,
+                                    lenses.push(format!("lens.mk ({ret} ∘ {ty}.{field}) (λ (o : {applied_ty}) i, {ret} {setter})",
+                                                        ret=self.monad().ret, ty=self.name_def_id(adt_def.did), applied_ty=self.transpile_ty(ty)?, field=field_name,
                                                         setter=self.update_struct(ty, field, "o", "i")?))
                                 },
                                 _ => throw!("unimplemented: lens on field | of {:?}", adt_def.adt_kind()),
@@ -571,7 +753,7 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
                         }
                     }
                     ProjectionElem::Index(ref index) =>
-                        lenses.push(format!("lens.index _ {}", self.get_operand(index)?.to_total())),
+                        lenses.push(format!("lens.index _ {}", self.index_to_nat(self.get_operand(index)?.to_total()))),
                     _ => throw!("unimplemented: lens on lvalue | {:?}", lv),
                 }
                 self.mk_lenses(base, lenses)
@@ -594,13 +776,13 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
             _ => source.clone()
         };
         if *dest == Lvalue::Local(RETURN_POINTER) &&
-            krate::try_unwrap_mut_ref(self.mir.return_ty).is_some() &&
+            self.try_unwrap_mut_ref(self.mir.return_ty).is_some() &&
             source != Lvalue::Local(Local::new(1)) {
             throw!("unimplemented: returning mutable reference to argument other than the first")
         }
         self.refs.insert(dest_local, source);
         let val = if lenses.is_empty() {
-            format!("@lens.id {}", self.transpile_ty(krate::try_unwrap_mut_ref(self.lvalue_ty(dest)).unwrap())?)
+            format!("@lens.id {}", self.transpile_ty(self.try_unwrap_mut_ref(self.lvalue_ty(dest)).unwrap())?)
         }
         else { format!("({})", lenses.into_iter().join(" ∘ₗ ")) };
         self.set_lvalue(0, dest, &val)
@@ -610,19 +792,22 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
         match *kind {
             StatementKind::Assign(ref lv, ref rv) => {
                 match *rv {
-                    Rvalue::Ref(_, BorrowKind::Mut, ref source) => {
+                    Rvalue::Ref(_, kind, ref source) if self.is_mut_borrow(kind, source) => {
                         let mut lenses = vec![];
                         let source = self.mk_lenses(source, &mut lenses)?;
                         let set = self.set_mut_ref(lv, lenses, source)?;
                         // probe lens to eagerly propagate out-of-bounds panics
-                        Ok(format!("{}do «$tmp» ← {};\n", set, self.get_lvalue(&lv.clone().deref())?.to_partial()))
+                        Ok(format!("{}{} «$tmp» ← {};\n", set, self.monad().bind, self.get_lvalue(&lv.clone().deref())?.to_partial(self)))
                     }
                     // move &mut
                     Rvalue::Cast(CastKind::Unsize, Operand::Consume(Lvalue::Local(source)), _)
                     | Rvalue::Use(Operand::Consume(Lvalue::Local(source)))
-                        if krate::try_unwrap_mut_ref(self.mir.local_decls[source].ty).is_some() =>
+                        if self.try_unwrap_mut_ref(self.mir.local_decls[source].ty).is_some() =>
                         self.set_mut_ref(lv, vec![], &Lvalue::Local(source)),
-                    _ => self.get_rvalue(rv)?.try_map(0, |rv| self.set_lvalue(1, lv, &rv)),
+                    // `box e` ~> `tmp = box T; *tmp = e`, so the write through the (transparent) box
+                    // already initializes it
+                    Rvalue::Box(_) => Ok("".to_string()),
+                    _ => self.get_rvalue(rv)?.try_map(self, 0, |rv| self.set_lvalue(1, lv, &rv)),
                 }
             }
             StatementKind::SetDiscriminant { .. } =>
@@ -635,10 +820,10 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
     fn transpile_basic_block_rec(&mut self, bb: BasicBlock, comp: &Component) -> TransResult {
         Ok(if comp.header == Some(bb) {
             // pass state to next iteration
-            format!("return (sum.inl {})\n", comp.state_val)
+            format!("{} (sum.inl {})\n", self.monad().ret, comp.state_val)
         } else if !comp.blocks.contains(&bb) {
             // leaving a loop
-            format!("do tmp__ ← {};\nreturn (sum.inr tmp__)", self.transpile_basic_block(bb, &comp.outer.unwrap())?)
+            format!("{} tmp__ ← {};\n{} (sum.inr tmp__)", self.monad().bind, self.transpile_basic_block(bb, &comp.outer.unwrap())?, self.monad().ret)
         } else {
             self.transpile_basic_block(bb, comp)?
         })
@@ -649,7 +834,7 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
         match call {
             &TerminatorKind::Call { ref args, destination: Some((ref lv, _)), .. } => {
                 let muts = args.iter().filter_map(|op| match *op {
-                    Operand::Consume(ref lv) => krate::try_unwrap_mut_ref(self.lvalue_ty(lv)).map(|_| lv),
+                    Operand::Consume(ref lv) => self.try_unwrap_mut_ref(self.lvalue_ty(lv)).map(|_| lv),
                     Operand::Constant(_) => None,
                 });
                 iter::once(lv).chain(muts).collect()
@@ -660,13 +845,13 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
 
     /// Locates the applicable definition of a method, given its name.
     // from trans::meth
-    fn get_impl_method<'t>(
+    pub fn get_impl_method<'t>(
         tcx: ty::TyCtxt<'a, 't, 't>,
         substs: &Substs<'t>,
         impl_def_id: DefId,
         impl_substs: &Substs<'t>,
         name: ast::Name,
-    ) -> (DefId, &'a Substs<'t>) {
+    ) -> (DefId, &'t Substs<'t>) {
         let trait_def_id = tcx.trait_id_of_impl(impl_def_id).unwrap();
         let trait_def = tcx.lookup_trait_def(trait_def_id);
 
@@ -691,27 +876,23 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
     }
 
     // All type generics including from parent items
-    /// Desparately tries to figure out a call target, including implicit (type) parameters
-    fn get_call_target(&self, func: &Operand<'tcx>) -> TransResult {
+    /// Desparately tries to figure out a call target, including implicit (type) parameters.
+    /// Also returns the statically resolved callee, if any.
+    fn get_call_target(&self, func: &Operand<'tcx>) -> TransResult<(String, Option<DefId>)> {
         match *func {
             Operand::Constant(Constant { literal: Literal::Item { mut def_id, substs, .. }, .. }) => {
-                if NUM_INTRINSICS.is_match(&self.name_def_id(def_id)) {
-                    let mut name = self.name_def_id(def_id);
-                    let ty = substs[0].as_type().unwrap();
-                    if ty.is_signed() {
-                        name += "_signed";
-                    }
-                    return Ok(format!("{} {}.bits", name, ty))
-                }
-
+                let substs = self.mono_substs(substs);
                 for ty in substs.types() {
-                    if krate::try_unwrap_mut_ref(ty).is_some() {
+                    if self.try_unwrap_mut_ref(ty).is_some() {
                         throw!("unimplemented: instantiating type parameter |of {} with {:?}",
                                self.tcx.item_path_str(def_id), ty);
                     }
                 }
+                if let Some((def_id, substs)) = self.resolve_instance(def_id, substs)? {
+                    return Ok((self.mono_name(def_id, substs), Some(def_id)))
+                }
                 let mut substs = substs.clone();
-                self.tcx.infer_ctxt(None, Some(ty::ParameterEnvironment::for_item(self.tcx, self.node_id())), ::rustc::traits::Reveal::All).enter(|infcx| -> TransResult {
+                self.tcx.infer_ctxt(None, Some(ty::ParameterEnvironment::for_item(self.tcx, self.node_id())), ::rustc::traits::Reveal::All).enter(|infcx| -> TransResult<_> {
                     match self.tcx.trait_of_item(def_id) {
                         Some (trait_def_id) => {
                             // from trans::meth::trans_method_callee
@@ -735,21 +916,112 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
                         LeanTyParam::TraitRef(_, _, trait_ref) =>
                             self.infer_trait_impl(trait_ref, &infcx)?.to_string(self)?,
                     })).try()?;
-                    Ok(format!("@{}", (self.name_def_id(def_id), ty_params).join(" ")))
+                    Ok((format!("@{}", (self.name_def_id(def_id), ty_params).join(" ")), Some(def_id)))
                 })
             }
             Operand::Constant(_) => unreachable!(),
-            Operand::Consume(ref lv) => Ok(self.get_lvalue(lv)?.to_total()),
+            Operand::Consume(ref lv) => Ok((self.get_lvalue(lv)?.to_total(), None)),
         }
     }
 
     fn return_expr(&self) -> String {
         let mut_args = self.mir.args_iter().filter_map(|arg| {
-            krate::try_unwrap_mut_ref(self.mir.local_decls[arg].ty).map(|_| self.local_name(arg))
+            self.try_unwrap_mut_ref(self.mir.local_decls[arg].ty).map(|_| self.local_name(arg))
         });
         // MIR sometimes doesn't assign unit return values?
         let ret = if self.mir.return_ty.is_nil() {"⋆"} else {"ret"};
-        format!("return ({})\n", (ret, mut_args).join(", "))
+        format!("{} ({})\n", self.monad().ret, (ret, mut_args.chain(self.state.vars())).join(", "))
+    }
+
+    fn costs(&self) -> &Costs {
+        self.config.cost.for_item(&krate::name_def_id(self.tcx, self.def_id))
+    }
+
+    /// `cont` charged with `cost` additional steps, see `Costs`
+    fn charge(&self, cost: Option<String>, cont: String) -> String {
+        match cost {
+            Some(ref cost) if !self.total => format!("{} ({}) (\n{})", self.monad().incr, cost, cont),
+            _ => cont,
+        }
+    }
+
+    /// Namespace of integer operations on the configured integer representation
+    fn num_prefix(&self) -> &'static str {
+        if self.config.bitvec_ints { "bv." } else { "" }
+    }
+
+    /// `(bv.to_nat idx)` for indexing into lists
+    fn index_to_nat(&self, idx: String) -> String {
+        if self.config.bitvec_ints { format!("(bv.to_nat {})", idx) } else { idx }
+    }
+
+    /// Failing computation, labelled with `reason` if requested
+    fn panic(&self, reason: &str) -> String {
+        if self.config.panic_reasons {
+            format!("{} {}\n", self.monad().panic, reason)
+        } else {
+            format!("{}\n", self.monad().fail)
+        }
+    }
+
+    /// `core::panicking::panic` at `lib.rs:3:5` ~> `"core.panicking.panic at lib.rs:3:5"`
+    fn call_site(&self, func: &Operand<'tcx>, span: Span) -> String {
+        let loc = self.tcx.sess.codemap().span_to_string(span);
+        krate::mk_lean_string_lit(&match *func {
+            // don't use `self.name_def_id`, which would add a dependency on the panicking function
+            Operand::Constant(Constant { literal: Literal::Item { def_id, .. }, .. }) =>
+                format!("{} at {}", krate::name_def_id(self.tcx, def_id), loc),
+            _ => loc,
+        })
+    }
+
+    /// `AssertMessage::Math(Overflow(Add))` ~> `(panic_reason.overflow "add")`
+    fn assert_reason(&self, msg: &AssertMessage<'tcx>) -> String {
+        match *msg {
+            AssertMessage::BoundsCheck { .. } => "panic_reason.bounds_check".to_string(),
+            AssertMessage::Math(ConstMathErr::Overflow(op)) =>
+                format!("(panic_reason.overflow {})", krate::mk_lean_string_lit(&format!("{:?}", op).to_lowercase())),
+            AssertMessage::Math(ConstMathErr::DivisionByZero) => "panic_reason.div_by_zero".to_string(),
+            AssertMessage::Math(ConstMathErr::RemainderByZero) => "panic_reason.rem_by_zero".to_string(),
+            AssertMessage::Math(ref err) =>
+                format!("(panic_reason.math {})", krate::mk_lean_string_lit(err.description())),
+        }
+    }
+
+    /// Drops of `RefCell` guards and `Rc`s give back their borrow or reference, all other drops
+    /// are no-ops
+    fn transpile_drop(&self, bb: BasicBlock, location: &Lvalue<'tcx>) -> TransResult {
+        let adt_def = match self.lvalue_ty(location).sty {
+            ty::TypeVariants::TyAdt(adt_def, _)
+                if krate::is_refcell_guard(self.tcx, adt_def.did) || krate::is_rc(self.tcx, adt_def.did) => adt_def,
+            _ => return Ok(String::new()),
+        };
+        let local = location.as_local().ok_or_else(|| format!("unimplemented: dropping |{:?}", location))?;
+        let moved = {
+            let mut moves = self.moves.borrow_mut();
+            if moves.is_none() {
+                *moves = Some(moves::Moves::new(self.mir));
+            }
+            moves.as_ref().unwrap().is_moved_at_terminator(bb, local)
+        };
+        match moved {
+            Some(true) => return Ok(String::new()),
+            Some(false) => {}
+            None => throw!("unimplemented: dropping conditionally moved |{:?}", location),
+        }
+        let release = krate::name_def_id(self.tcx, adt_def.did) + ".release";
+        if krate::is_rc(self.tcx, adt_def.did) {
+            return Ok(self.get_lvalue(location)?.map(self, 0, |rc| format!("{} heap__ ← {} {} heap__;\n", self.monad().bind, release, rc)))
+        }
+        // give back the borrow of the `RefCell` the guard points to
+        let src = self.deref_mut(location).ok_or_else(|| {
+            format!("unimplemented: dropping RefCell guard of unknown origin |{:?}", location)
+        })?;
+        let guard = self.get_lvalue(location)?.to_total();
+        self.get_lvalue(&src)?.try_map(self, 0, |ssrc| {
+            MaybeValue::partial(format!("{} {} {}", release, guard, ssrc))
+                .try_map(self, 1, |new| self.set_lvalue(2, &src, &new))
+        })
     }
 
     fn transpile_basic_block(&mut self, bb: BasicBlock, comp: &Component) -> TransResult {
@@ -762,33 +1034,38 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
             let (defs, _) = Component::defs_uses(comp.blocks.iter().filter(|bb| !l_comp.blocks.contains(bb)), self);
             let (l_defs, l_uses) = Component::defs_uses(l_comp.blocks.iter(), self);
             // vars that are used by l, but not (re)defined ~> parameters
-            let nonlocal_uses = self.mir.local_decls.indices().try_filter_map(|v| {
+            let nonlocals = self.mir.local_decls.indices().try_filter_map(|v| {
                 Ok(if l_uses.contains(&v) && !l_defs.contains(&v) {
-                    if krate::try_unwrap_mut_ref(self.lvalue_ty(&Lvalue::Local(v))).is_some() {
+                    if self.try_unwrap_mut_ref(self.lvalue_ty(&Lvalue::Local(v))).is_some() {
                         throw!("unimplemented: &mut loop parameter")
                     }
-                    Some(self.local_name(v))
+                    Some(v)
                 } else {None})
             })?.collect_vec();
+            let nonlocal_uses = nonlocals.iter().map(|&v| self.local_name(v)).collect_vec();
             // vars that are redefined by l ~> loop state
-            let (state_var_tys, state_vars): (Vec<_>, Vec<_>) = self.mir.local_decls.indices().try_filter_map(|v| -> TransResult<_> {
-                Ok(if defs.contains(&v) && l_defs.contains(&v) {
-                    let ty = self.transpile_ty(self.lvalue_ty(&Lvalue::Local(v)))?;
-                    Some((ty, self.local_name(v)))
-                } else { None })
-            })?.unzip();
+            let state_locals = self.mir.local_decls.indices().filter(|v| defs.contains(v) && l_defs.contains(v)).collect_vec();
+            let mut state_var_tys = state_locals.iter().map(|&v| self.transpile_ty(self.lvalue_ty(&Lvalue::Local(v)))).try()?.collect_vec();
+            let mut state_vars = state_locals.iter().map(|&v| self.local_name(v)).collect_vec();
+            state_var_tys.extend(self.state.tys(self.tcx));
+            state_vars.extend(self.state.vars());
             let state_ty = item::mk_tuple_ty(state_var_tys);
             l_comp.state_val = mk_tuple(&state_vars);
             let name = format!("{}.loop_{}", self.name(), bb.index());
-            let app = (name, nonlocal_uses).join(" ");
-            let ret_ty = format!("sem (sum ({}) {})", state_ty, self.ret_ty()?);
+            let app = (&name, nonlocal_uses).join(" ");
+            let ret_ty = format!("{} (sum ({}) {})", self.monad().ty, state_ty, self.ret_ty()?);
             let body = self.transpile_basic_block(bb, &l_comp)?;
+            let body = self.charge(self.costs().iteration(), body);
             self.prelude.push(format!("definition {} (state__ : {}) : {} :=\n{}", app,
                                       state_ty, ret_ty, detuplize("state__", &state_vars, &body)));
-            return Ok(format!("loop ({}) {}", app, l_comp.state_val))
+            let loop_spec = self.transpile_loop_spec(&name, l_comp.blocks, &l_comp.loops, &nonlocals, &state_locals,
+                                                     &state_vars, &state_ty);
+            self.prelude.extend(::trans::spec::or_comment(&format!("{}.invariant", name), loop_spec));
+            return Ok(format!("{} ({}) {}", self.monad().loop_, app, l_comp.state_val))
         }
 
         let data = &self.mir[bb];
+        let cost = self.costs().block(&data.statements.iter().map(|s| &s.kind).collect_vec());
         let stmts = data.statements.iter().map(|s| self.transpile_statement(&s.kind)).try()?;
         let terminator = match data.terminator {
             Some(ref terminator) => Some(match terminator.kind {
@@ -796,27 +1073,68 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
                     rec!(target)?,
                 If { ref cond, targets: (bb_if, bb_else) } =>
                     // TODO: this duplicates all code after the if
-                    self.get_operand(cond)?.try_map(0, |cond| Ok(format!(
+                    self.get_operand(cond)?.try_map(self, 0, |cond| Ok(format!(
                         "if {} = bool.tt then\n{}else\n{}", cond,
                         rec!(bb_if)?,
                         rec!(bb_else)?)))?,
                 Return => self.return_expr(),
+                // spec markers of the current loop, see `transpile_loop_spec`
+                Call { ref func, ref args, destination: Some((_, target)), .. }
+                    if comp.header.is_some() && self.loop_marker(func, args).is_some() =>
+                    rec!(target)?,
                 Call { ref func, ref args, destination: Some((_, target)), ..  } => {
-                    MaybeValue::try_map_multi(0, args.iter().map(|op| {
+                    MaybeValue::try_map_multi(self, 0, args.iter().map(|op| {
                         if let Operand::Consume(ref lv) = *op {
-                            if krate::try_unwrap_mut_ref(self.lvalue_ty(lv)).is_some() {
+                            if self.try_unwrap_mut_ref(self.lvalue_ty(lv)).is_some() {
                                 // dereference &mut arguments
                                 return self.get_lvalue(&lv.clone().deref())
                             }
                         }
                         self.get_operand(op)
                     }).try()?, |sargs| {
-                        let call_target = self.get_call_target(func)?;
-                        let call = (call_target, sargs).join(" ");
+                        let (call, callee, callee_state) = match self.get_intrinsic(func) {
+                            Some(intrinsic) => {
+                                let def_id = match *func {
+                                    Operand::Constant(Constant { literal: Literal::Item { def_id, .. }, .. }) => def_id,
+                                    _ => unreachable!(),
+                                };
+                                if self.total {
+                                    throw!("unimplemented: intrinsic call in pure function")
+                                }
+                                (self.transpile_intrinsic_call(intrinsic, func, args, sargs)?, Some(def_id), self.effects(def_id))
+                            }
+                            None => {
+                                // trait methods are judged by the implementation they resolve to
+                                let (target, callee) = self.get_call_target(func)?;
+                                // integers are not converted between representations, see the `int_repr` header
+                                if let Some(def_id) = callee {
+                                    if !def_id.is_local() && self.crate_bitvec_ints(def_id.krate) != self.config.bitvec_ints {
+                                        throw!("unimplemented: call into crate with a different int_repr |{}",
+                                               self.tcx.item_path_str(def_id))
+                                    }
+                                }
+                                let callee_state = callee.map_or(Effects::default(), |def_id| self.effects(def_id));
+                                let call = (target, sargs.into_iter().chain(callee_state.vars())).join(" ");
+                                // ghost functions translated to plain definitions
+                                let is_pure = callee.map_or(false, |def_id| self.pure_fn(def_id).is_some());
+                                let call = match (is_pure, self.total) {
+                                    (true, false) => format!("{} ({})", self.monad().ret, call),
+                                    (false, true) => throw!("unimplemented: partial call in pure function"),
+                                    _ => call,
+                                };
+                                (call, callee, callee_state)
+                            }
+                        };
+                        let call = if self.config.cost.enabled {
+                            let cost = self.costs().callee(callee.map(|def_id| krate::name_def_id(self.tcx, def_id)).as_ref().map(|path| &path[..]));
+                            format!("{} «$tmp» ← {};\n", self.monad().bind, self.charge(cost, call))
+                        } else {
+                            format!("{} «$tmp» ← {};\n", self.monad().step, call)
+                        };
 
-                        let (direct_dests, indirect_dests): (Vec<_>, Vec<_>) = self.call_return_dests(&terminator.kind).into_iter().enumerate().map(|(i, lv)| -> TransResult<_> {
+                        let (mut direct_dests, indirect_dests): (Vec<_>, Vec<_>) = self.call_return_dests(&terminator.kind).into_iter().enumerate().map(|(i, lv)| -> TransResult<_> {
                             let tmp = format!("«{}$»", self.local_name(lv.as_local().unwrap()));
-                            Ok(if krate::try_unwrap_mut_ref(self.lvalue_ty(lv)).is_some() {
+                            Ok(if self.try_unwrap_mut_ref(self.lvalue_ty(lv)).is_some() {
                                 if i == 0 {
                                     let source = lvalue_of_operand(&args[0]);
                                     // reborrow source into lv, using lens tmp
@@ -833,15 +1151,20 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
                                 }
                             })
                         }).try()?.unzip();
+                        direct_dests.extend(callee_state.vars());
                         let indirect_dests = indirect_dests.into_iter().filter_map(|x| x).rev().join("");
                         let rec = rec!(target)?;
-                        Ok(format!("dostep «$tmp» ← {};\n{}", call,
+                        Ok(format!("{}{}", call,
                                    detuplize("«$tmp»", &direct_dests[..], &(indirect_dests + &rec))))
                     })?
                 }
                 // diverging call
-                Call { destination: None, .. } | Unreachable =>
-                    "mzero\n".to_string(),
+                Call { ref func, destination: None, .. } => match self.get_intrinsic(func) {
+                    Some(Intrinsic::Abort) => self.panic("panic_reason.abort"),
+                    _ => self.panic(&format!("(panic_reason.explicit {})", self.call_site(func, terminator.source_info.span))),
+                },
+                Unreachable =>
+                    self.panic("panic_reason.unreachable"),
                 Switch { ref discr, ref adt_def, ref targets } => {
                     let arms = adt_def.variants.iter().zip(targets).map(|(var, &target)| -> TransResult<_> {
                         let vars = if var.ctor_kind == CtorKind::Fictive {
@@ -851,12 +1174,16 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
                         };
                         Ok(format!("| {} :=\n{}", (self.name_def_id(var.did), vars).join(" "), rec!(target)?))
                     }).try()?.join(" ");
-                    self.get_lvalue(discr)?.map(0, |discr| {
+                    self.get_lvalue(discr)?.map(self, 0, |discr| {
                         format!("match {} with\n{}end\n", discr, arms)
                     })
                 },
-                SwitchInt { ref discr, switch_ty: _, ref values, ref targets } => {
-                    self.get_lvalue(discr)?.try_map(0, |discr| {
+                SwitchInt { ref discr, switch_ty, ref values, ref targets } => {
+                    self.get_lvalue(discr)?.try_map(self, 0, |discr| {
+                        // match on the numeric value of bit vectors
+                        let discr = if self.config.bitvec_ints && switch_ty.is_integral() {
+                            format!("bv.to_{} {}", if switch_ty.is_signed() {"int"} else {"nat"}, discr)
+                        } else { discr };
                         let arms = values.iter().zip(targets).map(|(val, &target)| {
                             let val = match *val {
                                 ConstVal::Integral(i) => match i.int_type().unwrap() {
@@ -875,13 +1202,21 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
                                    arms.into_iter().chain(iter::once(fallback)).join("")))
                     })?
                 },
+                Assert { ref cond, expected, ref msg, target, .. }
+                    if self.config.panic_reasons && (self.config.overflow_checks || !is_overflow_check(msg)) => {
+                    let reason = self.assert_reason(msg);
+                    self.get_operand(cond)?.try_map(self, 0, |cond| Ok(format!(
+                        "{} {} {} (\n{})", self.monad().assert, reason, if expected { cond } else {
+                            format!("(bool.bnot {})", cond)
+                        }, rec!(target)?)))?
+                }
                 // out-of-bounds/overflow checks - already part of core/pre.lean
-                Assert { target, /*ref cond, expected,*/ .. } => rec!(target)?, /*self.get_operand(cond).map(0, |cond| {
-                    format!("cond {} mzero ({})", if expected {cond} else {
-                        format!("(bnot {})", cond)
-                    }, rec!(target))
-                }),*/
-                Drop { target, .. } => rec!(target)?,
+                // (or inherited overflow checks, which are disabled without `overflow_checks`)
+                Assert { target, .. } => rec!(target)?,
+                Drop { ref location, target, .. } => {
+                    let release = self.transpile_drop(bb, location)?;
+                    release + &rec!(target)?
+                }
                 DropAndReplace { ref location, ref value, target, .. } => {
                     self.transpile_statement(&StatementKind::Assign(location.clone(), Rvalue::Use(value.clone())))? +
                        &rec!(target)?
@@ -890,7 +1225,7 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
             }),
             None => None,
         };
-        Ok(stmts.chain(terminator).join(""))
+        Ok(self.charge(cost, stmts.chain(terminator).join("")))
     }
 
     pub fn transpile_mir(&mut self) -> TransResult {
@@ -900,8 +1235,12 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
     }
 
     fn ret_ty(&self) -> TransResult {
-        self.sup.ret_ty(&self.mir.args_iter().map(|arg| self.mir.local_decls[arg].ty).collect_vec(),
-                        self.mir.return_ty)
+        let ty = self.sup.ret_ty(&self.mir.args_iter().map(|arg| self.mir.local_decls[arg].ty).collect_vec(),
+                                 self.mir.return_ty)?;
+        Ok(if self.state == Effects::default() { ty } else {
+            // `(R × T)` ~> `(R × T × heap)`
+            format!("{} × {})", &ty[..ty.len() - 1], self.state.tys(self.tcx).join(" × "))
+        })
     }
 
     fn is_closure(&self) -> bool {
@@ -912,27 +1251,48 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
     }
 
     pub fn transpile_fn(mut self, mut name: String) -> TransResult {
+        self.state = self.effects(self.def_id);
         let param_names = self.mir.args_iter().map(|arg| self.local_name(arg)).collect_vec();
         let param_tys = self.mir.args_iter().map(|arg| {
-            if self.is_closure() && arg.index() > 1 && krate::try_unwrap_mut_ref(&self.mir.local_decls[arg].ty).is_some() {
+            if self.is_closure() && arg.index() > 1 && self.try_unwrap_mut_ref(&self.mir.local_decls[arg].ty).is_some() {
                 throw!("unimplemented: closure taking &mut")
             }
-            self.transpile_ty(krate::unwrap_mut_ref(&self.mir.local_decls[arg].ty))
+            self.transpile_ty(self.unwrap_mut_ref(&self.mir.local_decls[arg].ty))
         }).try()?.collect_vec();
-        let params = param_names.iter().zip(&param_tys).map(|(name, ty)| {
+        let mut params = param_names.iter().zip(&param_tys).map(|(name, ty)| {
             format!("({} : {})", name, ty)
         }).collect_vec();
+        if self.state != Effects::default() {
+            if self.is_closure() {
+                throw!("unimplemented: closure using implicit state |{:?}", self.state)
+            }
+            if self.state.globals {
+                self.add_dep(DefId::local(CRATE_DEF_INDEX));
+            }
+            params.extend(self.state.vars().into_iter().zip(self.state.tys(self.tcx)).map(|(var, ty)| {
+                format!("({} : {})", var, ty)
+            }));
+        }
 
-        let promoted = self.mir.promoted.iter_enumerated().map(|(idx, mir)| {
-            let body = FnTranspiler { mir: mir, ..self.clone() }.transpile_mir()?;
-            Ok(format!("do promoted_{} ←\n{};", idx.index(), body))
-        }).try()?;
-
-        let body = (promoted, self.transpile_mir()?).join("\n");
+        let body = match intrinsics::rc_model(self.tcx, self.def_id) {
+            // the unsafe implementation is replaced by the model in alloc/pre.lean
+            Some(model) if self.state.heap =>
+                (format!("alloc.handle.{}", model), param_names.iter().cloned().chain(self.state.vars())).join(" "),
+            _ => {
+                let promoted = self.mir.promoted.iter_enumerated().map(|(idx, mir)| {
+                    let body = FnTranspiler { mir: mir, state: Effects::default(), moves: Default::default(), ..self.clone() }.transpile_mir()?;
+                    Ok(format!("{} promoted_{} ←\n{};", self.monad().bind, idx.index(), body))
+                }).try()?;
+                (promoted, self.transpile_mir()?).join("\n")
+            }
+        };
 
         let ty_params = self.transpile_ty_params(self.def_id)?;
         let (closure_def, closure_impl) = if self.is_closure() {
-            let closure_ty = unwrap_refs(krate::unwrap_mut_ref(&self.mir.local_decls[Local::new(1)].ty));
+            let closure_ty = unwrap_refs(self.unwrap_mut_ref(&self.mir.local_decls[Local::new(1)].ty));
+            if self.is_interior_mut(closure_ty) {
+                throw!("unimplemented: closure capturing interior mutable state")
+            }
             let upvar_tys = match closure_ty.sty {
                 ty::TypeVariants::TyClosure(_, ref substs) => substs.upvar_tys(self.def_id, self.tcx),
                 _ => unreachable!(),
@@ -962,12 +1322,21 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
         }
         let body = if is_rec {
             // FIXME: not actually implemented yet
-            format!("fix_opt (λ{}, {})", name, body)
+            format!("{} (λ{}, {})", self.monad().fix, name, body)
         } else { body };
-        Ok(if self.prelude.is_empty() && !self.is_closure() {
-            format!("definition {} : sem {} :=\n{}",
-                    (name, ty_params.iter().map(|p| p.to_string()).chain(params)).join(" "),
-                    self.ret_ty()?, body)
+        let specs = if self.is_closure() || self.total { vec![] } else {
+            let args = param_names.iter().cloned().chain(self.state.vars()).collect_vec();
+            ::trans::spec::or_comment(&format!("{}.spec", name), self.transpile_spec(&name, &ty_params, &params, &args)).into_iter()
+                .chain(::trans::spec::or_comment(&format!("{}.inv", name),
+                                                 self.transpile_inv_preservation(&name, &ty_params, &params, &args)))
+                .collect_vec()
+        };
+        // the monad type is empty for ghost functions
+        let ret_ty = format!("{} {}", self.monad().ty, self.ret_ty()?).trim_left().to_string();
+        let def = if self.prelude.is_empty() && !self.is_closure() {
+            format!("definition {} : {} :=\n{}",
+                    (&name, ty_params.iter().map(|p| p.to_string()).chain(params)).join(" "),
+                    ret_ty, body)
         } else {
             fn format_params<It: IntoIterator<Item=String>>(prefix: &str, params: It) -> String {
                 let params = params.into_iter().collect_vec();
@@ -978,7 +1347,7 @@ impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
             format!("section
 {}
 
-definition {} : sem {} :=
+definition {} : {} :=
 {}
 
 {}end",
@@ -986,7 +1355,8 @@ definition {} : sem {} :=
                     &closure_def +
                     &format_params("include", ty_params.iter().map(|p| p.name().to_string())) +
                     &self.prelude.iter().join("\n\n"),
-                    (name, params).join(" "), self.ret_ty()?, body, closure_impl)
-        })
+                    (name, params).join(" "), ret_ty, body, closure_impl)
+        };
+        Ok((def, specs).join("\n\n"))
     }
 }
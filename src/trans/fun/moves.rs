@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use rustc::mir::*;
+use rustc::mir::visit::{LvalueContext, Visitor};
+use rustc_data_structures::indexed_vec::IndexVec;
+
+/// Locals that have been moved out of on some/all paths
+#[derive(Clone, Default, PartialEq, Eq)]
+struct MoveState {
+    maybe: HashSet<Local>,
+    definitely: HashSet<Local>,
+}
+
+impl MoveState {
+    fn join(&self, other: &MoveState) -> MoveState {
+        MoveState {
+            maybe: self.maybe.union(&other.maybe).cloned().collect(),
+            definitely: self.definitely.intersection(&other.definitely).cloned().collect(),
+        }
+    }
+
+    fn move_out(&mut self, local: Local) {
+        self.maybe.insert(local);
+        self.definitely.insert(local);
+    }
+
+    fn init(&mut self, lv: &Lvalue) {
+        if let Lvalue::Local(local) = *lv {
+            self.maybe.remove(&local);
+            self.definitely.remove(&local);
+        }
+    }
+}
+
+struct MoveCollector<'s> {
+    state: &'s mut MoveState,
+}
+
+impl<'s, 'tcx> Visitor<'tcx> for MoveCollector<'s> {
+    fn visit_lvalue(&mut self, lvalue: &Lvalue<'tcx>, context: LvalueContext<'tcx>, location: Location) {
+        if let (&Lvalue::Local(local), LvalueContext::Consume) = (lvalue, context) {
+            self.state.move_out(local);
+        }
+        self.super_lvalue(lvalue, context, location);
+    }
+}
+
+/// Whether a `Drop` terminator actually drops its value. Our MIR is not drop-elaborated yet, so
+/// values that have been moved out of are still dropped at the end of their scope.
+pub struct Moves<'a, 'tcx: 'a> {
+    mir: &'a Mir<'tcx>,
+    entry: IndexVec<BasicBlock, Option<MoveState>>,
+}
+
+impl<'a, 'tcx> Moves<'a, 'tcx> {
+    pub fn new(mir: &'a Mir<'tcx>) -> Moves<'a, 'tcx> {
+        let mut moves = Moves { mir: mir, entry: IndexVec::from_elem(None, mir.basic_blocks()) };
+        moves.entry[START_BLOCK] = Some(MoveState::default());
+        let mut worklist = vec![START_BLOCK];
+        while let Some(bb) = worklist.pop() {
+            let exit = moves.before_terminator(bb);
+            let data = &mir[bb];
+            for succ in data.terminator().successors().iter().cloned() {
+                let mut state = exit.clone();
+                match data.terminator().kind {
+                    TerminatorKind::Call { destination: Some((ref lv, target)), .. } if target == succ =>
+                        state.init(lv),
+                    TerminatorKind::DropAndReplace { ref location, .. } => state.init(location),
+                    _ => {}
+                }
+                let joined = match moves.entry[succ] {
+                    Some(ref old) => old.join(&state),
+                    None => state,
+                };
+                if moves.entry[succ].as_ref() != Some(&joined) {
+                    moves.entry[succ] = Some(joined);
+                    worklist.push(succ);
+                }
+            }
+        }
+        moves
+    }
+
+    fn before_terminator(&self, bb: BasicBlock) -> MoveState {
+        let mut state = self.entry[bb].clone().unwrap_or_else(MoveState::default);
+        let data = &self.mir[bb];
+        for (i, stmt) in data.statements.iter().enumerate() {
+            let location = Location { block: bb, statement_index: i };
+            if let StatementKind::Assign(ref lv, ref rv) = stmt.kind {
+                MoveCollector { state: &mut state }.visit_rvalue(rv, location);
+                state.init(lv);
+            }
+        }
+        if let TerminatorKind::Call { ref args, .. } = data.terminator().kind {
+            let location = Location { block: bb, statement_index: data.statements.len() };
+            for arg in args {
+                MoveCollector { state: &mut state }.visit_operand(arg, location);
+            }
+        }
+        state
+    }
+
+    /// `Some(true)` if `local` has been moved out of on all paths to the terminator of `bb`,
+    /// `Some(false)` if on none, `None` if it depends on the path
+    pub fn is_moved_at_terminator(&self, bb: BasicBlock, local: Local) -> Option<bool> {
+        let state = self.before_terminator(bb);
+        if state.definitely.contains(&local) {
+            Some(true)
+        } else if state.maybe.contains(&local) {
+            None
+        } else {
+            Some(false)
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::iter;
+
+use itertools::Itertools;
+
+use rustc::hir;
+
+use rustc::middle::const_val::ConstVal;
+use rustc::mir::*;
+
+use util::*;
+use trans::fun::{Effects, FnTranspiler, get_tuple_elem};
+use trans::item::{LeanTyParam, detuplize};
+use trans::TransResult;
+
+impl<'a, 'tcx> FnTranspiler<'a, 'tcx> {
+    /// `theorem f.spec params (pre) : sem.terminates_with (λ result, post) (f params) := sorry` for
+    /// functions with `requires`/`ensures` specs, to be proved in the crate's `thy.lean`
+    pub fn transpile_spec(&self, name: &str, ty_params: &[LeanTyParam], params: &[String], args: &[String]) -> TransResult<Option<String>> {
+        let (requires, ensures) = (self.spec_attrs(self.def_id, "requires"), self.spec_attrs(self.def_id, "ensures"));
+        if requires.is_empty() && ensures.is_empty() {
+            return Ok(None)
+        }
+        let mut scope = self.mir.args_iter().filter_map(|arg| self.mir.local_decls[arg].name.map(|name| {
+            (name.to_string(), self.local_name(arg), self.mir.local_decls[arg].ty)
+        })).collect_vec();
+        scope.push(("result".to_string(), "result".to_string(), self.mir.return_ty));
+        let requires = requires.iter().enumerate().map(|(i, src)| -> TransResult<_> {
+            Ok(format!("(requires{} : {})", i, self.spec_prop(src, &scope)?))
+        }).try()?.collect_vec();
+        let app = (name, args).join(" ");
+        let goal = if ensures.is_empty() {
+            format!("{} ({})", self.monad().terminates, app)
+        } else {
+            let ret_has_mut_args = self.mir.args_iter().any(|arg| {
+                self.try_unwrap_mut_ref(self.mir.local_decls[arg].ty).is_some()
+            });
+            if ret_has_mut_args || self.state != Effects::default() {
+                throw!("unimplemented: spec |`ensures` of function returning &mut arguments or implicit state")
+            }
+            let ensures = ensures.iter().map(|src| self.spec_prop(src, &scope)).try()?.join(" ∧ ");
+            format!("{} (λ result, {}) ({})", self.monad().terminates_with, ensures, app)
+        };
+        Ok(Some(format!("theorem {} :\n  {} :=\nsorry",
+                        (name.to_string() + ".spec", ty_params.iter().map(|p| p.to_string()).chain(params.iter().cloned()).chain(requires)).join(" "),
+                        goal)))
+    }
+
+    /// `theorem f.inv params (invs) : sem.on_return (λ result, invs') (f params) := sorry` for public methods
+    /// taking or returning structs with invariants, stating that the invariants of the inputs are preserved by
+    /// the outputs (return value and `&mut` arguments)
+    pub fn transpile_inv_preservation(&self, name: &str, ty_params: &[LeanTyParam], params: &[String], args: &[String]) -> TransResult<Option<String>> {
+        let is_public = match self.tcx.map.get(self.node_id()) {
+            hir::map::NodeImplItem(&hir::ImplItem { vis: hir::Visibility::Public, .. }) => true,
+            // trait methods are as public as the trait
+            hir::map::NodeImplItem(_) => self.tcx.impl_of_method(self.def_id).map_or(false, |impl_did| {
+                self.tcx.impl_trait_ref(impl_did).is_some()
+            }),
+            _ => false,
+        };
+        if !is_public {
+            return Ok(None)
+        }
+        // named by argument position, as argument names may be escaped identifiers
+        let hyps = self.mir.args_iter().zip(args).enumerate().filter_map(|(i, (arg, sarg))| {
+            self.invariant_of(self.mir.local_decls[arg].ty).map(|inv| format!("(inv_{} : {} {})", i, inv, sarg))
+        }).collect_vec();
+        // components of the result: return value, `&mut` arguments and implicit state
+        let mut_args = self.mir.args_iter().filter(|&arg| {
+            self.try_unwrap_mut_ref(self.mir.local_decls[arg].ty).is_some()
+        }).collect_vec();
+        let outputs = iter::once(self.mir.return_ty).chain(mut_args.iter().map(|&arg| self.mir.local_decls[arg].ty)).collect_vec();
+        let len = outputs.len() + self.state.vars().len();
+        let invs = outputs.into_iter().enumerate().filter_map(|(i, ty)| self.invariant_of(ty).map(|inv| {
+            format!("{} {}", inv, if len == 1 { "result".to_string() } else { get_tuple_elem("result", i, len) })
+        })).collect_vec();
+        if invs.is_empty() {
+            return Ok(None)
+        }
+        Ok(Some(format!("theorem {} :\n  {} (λ result, {}) ({}) :=\nsorry",
+                        (name.to_string() + ".inv", ty_params.iter().map(|p| p.to_string()).chain(params.iter().cloned()).chain(hyps)).join(" "),
+                        self.monad().on_return, invs.join(" ∧ "), (name, args).join(" "))))
+    }
+
+    /// Calls of marker functions `#[loop_invariant] fn invariant(_: &str) {}` and `#[loop_variant] fn variant(_: &str) {}`
+    /// (`#![feature(custom_attribute)]`), e.g. from `macro_rules! invariant { ($e:expr) => { invariant(stringify!($e)) } }`,
+    /// inside a loop body
+    pub fn loop_marker(&self, func: &Operand<'tcx>, args: &[Operand<'tcx>]) -> Option<(&'static str, String)> {
+        let def_id = match *func {
+            Operand::Constant(Constant { literal: Literal::Item { def_id, .. }, .. }) => def_id,
+            _ => return None,
+        };
+        let attrs = self.tcx.get_attrs(def_id);
+        let kind = if attrs.iter().any(|attr| attr.check_name("loop_invariant")) {
+            "invariant"
+        } else if attrs.iter().any(|attr| attr.check_name("loop_variant")) {
+            "variant"
+        } else {
+            return None
+        };
+        match args {
+            &[Operand::Constant(Constant { literal: Literal::Value { value: ConstVal::Str(ref src) }, .. })] =>
+                Some((kind, src.to_string())),
+            _ => None,
+        }
+    }
+
+    /// Named invariant and variant of the loop `name` over its state, with lemma stubs for preservation and
+    /// decrease to be discharged for `loop.terminates_with_variant`
+    pub fn transpile_loop_spec(&self, name: &str, blocks: &[BasicBlock], nested: &[Vec<BasicBlock>],
+                               params: &[Local], state: &[Local], state_vars: &[String], state_ty: &str)
+                               -> TransResult<Vec<String>> {
+        let markers = blocks.iter().filter(|bb| !nested.iter().any(|l| l.contains(bb))).filter_map(|&bb| {
+            match self.mir[bb].terminator().kind {
+                TerminatorKind::Call { ref func, ref args, .. } => self.loop_marker(func, args),
+                _ => None,
+            }
+        }).collect_vec();
+        if markers.is_empty() {
+            return Ok(vec![])
+        }
+        let scope = state.iter().chain(params).filter_map(|&v| self.mir.local_decls[v].name.map(|var| {
+            (var.to_string(), self.local_name(v), self.mir.local_decls[v].ty)
+        })).collect_vec();
+        let binders = params.iter().map(|&v| -> TransResult<_> {
+            Ok(format!("({} : {})", self.local_name(v), self.transpile_ty(self.unwrap_mut_ref(self.mir.local_decls[v].ty))?))
+        }).try()?.collect_vec();
+        let app = |def: &str, state: &str| (def, params.iter().map(|&v| self.local_name(v)).chain(Some(state.to_string()))).join(" ");
+        let mut defs = vec![];
+        let invariants = markers.iter().filter(|&&(kind, _)| kind == "invariant").map(|&(_, ref src)| {
+            self.spec_prop(src, &scope)
+        }).try()?.collect_vec();
+        let invariant = format!("{}.invariant", name);
+        defs.push(format!("definition {} (state__ : {}) : Prop :=\n{}", (&invariant, &binders).join(" "), state_ty,
+                          detuplize("state__", state_vars, &if invariants.is_empty() { "true".to_string() } else {
+                              invariants.join(" ∧ ")
+                          })));
+        defs.push(format!("lemma {}.preserve {} (state__ : {}) (inv : {}) :\n  \
+                           {} (λ r, sum.rec_on r (λ state', {}) (λ ret, true)) ({}) :=\nsorry",
+                          name, binders.join(" "), state_ty, app(&invariant, "state__"),
+                          self.monad().terminates_with, app(&invariant, "state'"), app(name, "state__")));
+        match markers.iter().filter(|&&(kind, _)| kind == "variant").collect_vec()[..] {
+            [] => {}
+            [&(_, ref src)] => {
+                let variant = format!("{}.variant", name);
+                defs.push(format!("definition {} (state__ : {}) : ℕ :=\n{}", (&variant, &binders).join(" "), state_ty,
+                                  detuplize("state__", state_vars, &self.spec_term(src, &scope)?)));
+                defs.push(format!("lemma {}.decrease {} (state__ : {}) (inv : {}) :\n  \
+                                   {} (λ r, sum.rec_on r (λ state', {} < {}) (λ ret, true)) ({}) :=\nsorry",
+                                  name, binders.join(" "), state_ty, app(&invariant, "state__"),
+                                  self.monad().terminates_with, app(&variant, "state'"), app(&variant, "state__"), app(name, "state__")));
+            }
+            _ => throw!("invalid spec |more than one variant for loop {}", name),
+        }
+        Ok(defs)
+    }
+}
+
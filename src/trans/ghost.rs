@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use syntax::ast;
+
+use rustc::hir::def_id::DefId;
+use rustc::ty::TyCtxt;
+
+use trans::fun::{Effects, FnTranspiler, has_mir};
+use trans::item::ItemTranspiler;
+use trans::krate::{self, CrateTranspiler};
+use trans::monad;
+
+// Ghost functions, marked `#[spec]` (`#![feature(custom_attribute)]`) or `#[cfg(spec)]`, exist only for use in specs
+// and are translated as dependencies of the specs calling them. With `spec_cfg = true`, rustc is invoked with
+// `--cfg spec` so that the latter are seen by the translation only.
+
+fn is_spec_attr(attr: &ast::Attribute) -> bool {
+    attr.check_name("spec") || attr.check_name("cfg") && attr.meta_item_list().map_or(false, |items| {
+        items.iter().any(|item| item.check_name("spec"))
+    })
+}
+
+pub fn is_spec_fn(tcx: TyCtxt, def_id: DefId) -> bool {
+    has_mir(tcx, def_id) && tcx.get_attrs(def_id).iter().any(is_spec_attr)
+}
+
+impl<'a, 'tcx> CrateTranspiler<'a, 'tcx> {
+    /// Indexes the crate's ghost functions by their Rust names, under which specs can call them. Calls of
+    /// names shared by several ghost functions are rejected, see `transpile_spec_expr`.
+    pub fn collect_spec_fns(&mut self, def_ids: &[DefId]) {
+        let mut spec_fns = HashMap::new();
+        for &def_id in def_ids {
+            if is_spec_fn(self.tcx, def_id) {
+                spec_fns.entry(self.tcx.item_name(def_id).to_string()).or_insert_with(Vec::new).push(def_id);
+            }
+        }
+        self.spec_fns = spec_fns;
+    }
+}
+
+impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
+    /// Translation of a ghost function as a plain `definition f params : T`, if it is free of partial operations
+    /// and effects and only calls other such functions
+    pub fn pure_fn(&self, def_id: DefId) -> Option<String> {
+        if let Some(def) = self.pure_fns.borrow().get(&def_id) {
+            return def.clone()
+        }
+        // (mutually) recursive calls are not total by construction
+        self.pure_fns.borrow_mut().insert(def_id, None);
+        let def = if !is_spec_fn(self.tcx, def_id) || self.is_mono_item(def_id) || self.effects(def_id) != Effects::default() {
+            None
+        } else {
+            let item = ItemTranspiler { sup: self.sup, def_id: def_id, substs: None };
+            let mir = self.tcx.item_mir(def_id);
+            match FnTranspiler::new_total(&item, &*mir).transpile_fn(krate::name_def_id(self.tcx, def_id)) {
+                Ok(ref def) if def.contains(monad::PARTIAL) => None,
+                Ok(def) => Some(def),
+                Err(_) => None,
+            }
+        };
+        self.pure_fns.borrow_mut().insert(def_id, def.clone());
+        def
+    }
+}
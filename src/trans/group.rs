@@ -0,0 +1,317 @@
+use std::collections::HashSet;
+use std::iter;
+use std::rc::Rc;
+
+use itertools::Itertools;
+
+use rustc::hir::def::CtorKind;
+use rustc::hir::def_id::DefId;
+use rustc::ty::{self, Ty, TyCtxt};
+
+use util::*;
+use trans::item::ItemTranspiler;
+use trans::krate::{self, CrateTranspiler};
+use trans::TransResult;
+
+/// Structs and enums of a crate that (mutually) recurse through their fields, translated as a single
+/// `inductive ... with ...` declaration
+pub struct TypeGroup<'tcx> {
+    /// sorted by name; the first one emits the declaration of the whole group
+    pub members: Vec<DefId>,
+    /// other types applied to members inside member fields, e.g. `Option<Box<Tree>>`. Lean does not
+    /// support such nested occurrences, so they are replaced by auxiliary inductive types with conversions
+    /// `wrap` and `unwrap`.
+    pub nested: Vec<(Ty<'tcx>, String)>,
+}
+
+impl<'tcx> TypeGroup<'tcx> {
+    /// Auxiliary type replacing `ty`, if it is a nested occurrence
+    pub fn nested_ty(&self, ty: Ty<'tcx>) -> Option<&str> {
+        let ty = strip_boxes(ty);
+        self.nested.iter().find(|&&(nested, _)| nested == ty).map(|&(_, ref aux)| &aux[..])
+    }
+
+    fn mentions_member(&self, ty: Ty<'tcx>) -> bool {
+        ty.walk().any(|ty| match ty.sty {
+            ty::TypeVariants::TyAdt(adt_def, _) => self.members.contains(&adt_def.did),
+            _ => false,
+        })
+    }
+}
+
+/// `Box<T>` is transparent
+fn strip_boxes<'tcx>(ty: Ty<'tcx>) -> Ty<'tcx> {
+    match ty.sty {
+        ty::TypeVariants::TyBox(ty) => strip_boxes(ty),
+        _ => ty,
+    }
+}
+
+/// `Vec<T>` is modelled as a `list T` in collections/pre.lean
+fn is_vec(tcx: TyCtxt, did: DefId) -> bool {
+    krate::name_def_id(tcx, did) == "collections.vec.Vec"
+}
+
+/// Structs and enums from the same crate mentioned in the fields of `did`
+fn field_adts(tcx: TyCtxt, did: DefId) -> Vec<DefId> {
+    tcx.lookup_adt_def(did).all_fields().flat_map(|f| f.unsubst_ty().walk()).filter_map(|ty| match ty.sty {
+        ty::TypeVariants::TyAdt(adt_def, _) if adt_def.did.krate == did.krate &&
+            adt_def.adt_kind() != ty::AdtKind::Union => Some(adt_def.did),
+        _ => None,
+    }).collect()
+}
+
+fn reachable_adts(tcx: TyCtxt, did: DefId) -> HashSet<DefId> {
+    let mut seen = HashSet::new();
+    let mut todo = field_adts(tcx, did);
+    while let Some(did) = todo.pop() {
+        if seen.insert(did) {
+            todo.extend(field_adts(tcx, did));
+        }
+    }
+    seen
+}
+
+fn compute_type_group<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>, did: DefId) -> Option<TypeGroup<'tcx>> {
+    let reachable = reachable_adts(tcx, did);
+    if !reachable.contains(&did) {
+        return None
+    }
+    let mut members = reachable.into_iter().filter(|&other| reachable_adts(tcx, other).contains(&did)).collect_vec();
+    members.sort_by_key(|&did| krate::name_def_id(tcx, did));
+    let mut group = TypeGroup { members: members, nested: vec![] };
+
+    let leader = krate::name_def_id(tcx, group.members[0]);
+    let mut todo = group.members.iter().flat_map(|&did| {
+        tcx.lookup_adt_def(did).all_fields().map(|f| f.unsubst_ty()).collect_vec()
+    }).collect_vec();
+    let mut i = 0;
+    while i < todo.len() {
+        let ty = strip_boxes(todo[i]);
+        i += 1;
+        if let ty::TypeVariants::TyAdt(adt_def, substs) = ty.sty {
+            if group.members.contains(&adt_def.did) || !group.mentions_member(ty) || group.nested_ty(ty).is_some() {
+                continue
+            }
+            let aux = format!("{}.nested_{}", leader, group.nested.len());
+            group.nested.push((ty, aux));
+            if is_vec(tcx, adt_def.did) {
+                todo.push(substs.type_at(0));
+            } else {
+                todo.extend(adt_def.all_fields().map(|f| f.ty(tcx, substs)));
+            }
+        }
+    }
+    Some(group)
+}
+
+impl<'a, 'tcx> CrateTranspiler<'a, 'tcx> {
+    /// The group of mutually recursive types `did` is part of, if any
+    pub fn type_group(&self, did: DefId) -> Option<Rc<TypeGroup<'tcx>>> {
+        if let Some(group) = self.type_groups.borrow().get(&did) {
+            return group.clone()
+        }
+        let group = compute_type_group(self.tcx, did).map(Rc::new);
+        self.type_groups.borrow_mut().insert(did, group.clone());
+        group
+    }
+}
+
+impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
+    /// Auxiliary type of a field of an ADT, which has to be converted from/to the field's actual type
+    pub fn nested_field(&self, adt_did: DefId, field_ty: Ty<'tcx>) -> Option<String> {
+        self.type_group(adt_did).and_then(|group| group.nested_ty(field_ty).map(ToString::to_string))
+    }
+
+    fn transpile_group_ty(&self, group: &TypeGroup<'tcx>, ty: Ty<'tcx>) -> TransResult {
+        if let Some(aux) = group.nested_ty(ty) {
+            return Ok(format!("({})", self.mk_applied_ty(aux, self.tcx.item_generics(self.def_id))))
+        }
+        match strip_boxes(ty).sty {
+            ty::TypeVariants::TyAdt(adt_def, substs) if group.members.contains(&adt_def.did) => {
+                if !substs.types().enumerate().all(|(i, ty)| match ty.sty {
+                    ty::TypeVariants::TyParam(p) => p.idx as usize == i,
+                    _ => false,
+                }) {
+                    throw!("unimplemented: non-uniform recursive type |{:?}", ty)
+                }
+                self.transpile_ty(ty)
+            }
+            _ if group.mentions_member(ty) => throw!("unimplemented: recursive type through |{:?}", ty),
+            _ => self.transpile_ty(ty),
+        }
+    }
+
+    /// `x` of a field of type `ty` converted from its auxiliary type, if any
+    fn unwrap_nested(&self, group: &TypeGroup<'tcx>, ty: Ty<'tcx>, x: &str) -> String {
+        match group.nested_ty(ty) {
+            Some(aux) => format!("({}.unwrap {})", aux, x),
+            None => x.to_string(),
+        }
+    }
+
+    fn wrap_nested(&self, group: &TypeGroup<'tcx>, ty: Ty<'tcx>, x: &str) -> String {
+        match group.nested_ty(ty) {
+            Some(aux) => format!("({}.wrap {})", aux, x),
+            None => x.to_string(),
+        }
+    }
+
+    fn implicit_ty_params(&self) -> String {
+        self.tcx.item_generics(self.def_id).types.iter().map(|p| format!("{{{} : Type₁}} ", p.name)).join("")
+    }
+
+    /// Struct (variant) with a single constructor `mk` and derived field accessors
+    fn transpile_group_struct(&self, group: &TypeGroup<'tcx>, name: &str, variant: ty::VariantDef<'tcx>)
+                              -> TransResult<(String, String)> {
+        let generics = self.tcx.item_generics(self.def_id);
+        let applied_ty = self.mk_applied_ty(name, generics);
+        let fields = variant.fields.iter().map(|f| self.transpile_group_ty(group, f.unsubst_ty())).try()?;
+        let decl = format!("{} :=\n| mk {{}} : {}",
+                           (name, generics.types.iter().map(|p| format!("({} : Type₁)", p.name))).join(" "),
+                           fields.chain(iter::once(applied_ty.clone())).join(" → "));
+        let vars = (0..variant.fields.len()).map(|i| format!("x{}", i)).collect_vec();
+        let accessors = variant.fields.iter().enumerate().map(|(i, f)| -> TransResult {
+            Ok(format!("definition {}.{} {}(self : {}) : {} :=\nmatch self with {} := {} end",
+                       name, self.mk_lean_name(&*f.name.as_str()), self.implicit_ty_params(), applied_ty,
+                       self.transpile_ty(f.unsubst_ty())?,
+                       (format!("{}.mk", name), vars.iter()).join(" "),
+                       self.unwrap_nested(group, f.unsubst_ty(), &vars[i])))
+        }).try()?.join("\n\n");
+        Ok((decl, accessors))
+    }
+
+    /// Constructors of an auxiliary type and its conversions from/to the nested type
+    fn transpile_nested(&self, group: &TypeGroup<'tcx>, ty: Ty<'tcx>, aux: &str) -> TransResult<(String, String)> {
+        let generics = self.tcx.item_generics(self.def_id);
+        let applied_aux = self.mk_applied_ty(aux, generics);
+        let decl_head = (aux, generics.types.iter().map(|p| format!("({} : Type₁)", p.name))).join(" ");
+        let lean_ty = self.transpile_ty(ty)?;
+        let (adt_def, substs) = match ty.sty {
+            ty::TypeVariants::TyAdt(adt_def, substs) => (adt_def, substs),
+            _ => unreachable!(),
+        };
+        let params = self.implicit_ty_params();
+
+        if is_vec(self.tcx, adt_def.did) {
+            let elem = substs.type_at(0);
+            let vec = self.name_def_id(adt_def.did);
+            let decl = format!("{} :=\n| nil {{}} : {applied}\n| cons {{}} : {} → {applied} → {applied}",
+                               decl_head, self.transpile_group_ty(group, elem)?, applied=applied_aux);
+            let convs = format!("definition {aux}.unwrap {params}: {applied} → {ty}
+| {aux}.nil := {vec}.mk []
+| ({aux}.cons x xs) := {vec}.mk ({} :: {vec}.buf ({aux}.unwrap xs))
+
+definition {aux}.wrap_list {params}: list {elem} → {applied}
+| [] := {aux}.nil
+| (x :: xs) := {aux}.cons {} ({aux}.wrap_list xs)
+
+definition {aux}.wrap {params}(v : {ty}) : {applied} := {aux}.wrap_list ({vec}.buf v)",
+                                self.unwrap_nested(group, elem, "x"), self.wrap_nested(group, elem, "x"),
+                                aux=aux, params=params, applied=applied_aux, ty=lean_ty, vec=vec,
+                                elem=self.transpile_ty(elem)?);
+            return Ok((decl, convs))
+        }
+
+        let mut ctors = vec![];
+        let mut unwraps = vec![];
+        let mut wraps = vec![];
+        for variant in &adt_def.variants {
+            let ctor = match adt_def.adt_kind() {
+                ty::AdtKind::Enum => self.mk_lean_name(variant.name),
+                _ => "mk".to_string(),
+            };
+            let field_tys = variant.fields.iter().map(|f| f.ty(self.tcx, substs)).collect_vec();
+            let vars = (0..field_tys.len()).map(|i| format!("x{}", i)).collect_vec();
+            let fields = field_tys.iter().map(|&ty| self.transpile_group_ty(group, ty)).try()?;
+            ctors.push(format!("| {} {{}} : {}", ctor, fields.chain(iter::once(applied_aux.clone())).join(" → ")));
+
+            let unwrapped = field_tys.iter().zip(&vars).map(|(&ty, x)| self.unwrap_nested(group, ty, x)).collect_vec();
+            let wrapped = field_tys.iter().zip(&vars).map(|(&ty, x)| self.wrap_nested(group, ty, x)).collect_vec();
+            let aux_pat = format!("({})", (format!("{}.{}", aux, ctor), vars.iter()).join(" "));
+            // patterns and values of the nested type
+            let (pat, val) = match (adt_def.adt_kind(), variant.ctor_kind) {
+                (ty::AdtKind::Enum, CtorKind::Fictive) => {
+                    let name = self.name_def_id(variant.did);
+                    (format!("({} ({}))", name, (format!("{}.struct.mk", name), vars.iter()).join(" ")),
+                     format!("({} ({}))", name, (format!("{}.struct.mk", name), unwrapped).join(" ")))
+                }
+                (ty::AdtKind::Enum, _) => {
+                    let name = self.name_def_id(variant.did);
+                    (format!("({})", (&name, vars.iter()).join(" ")), format!("({})", (&name, unwrapped).join(" ")))
+                }
+                _ => {
+                    let name = format!("{}.mk", self.name_def_id(adt_def.did));
+                    (format!("({})", (&name, vars.iter()).join(" ")), format!("({})", (&name, unwrapped).join(" ")))
+                }
+            };
+            unwraps.push(format!("| {} := {}", aux_pat, val));
+            wraps.push(format!("| {} := ({})", pat, (format!("{}.{}", aux, ctor), wrapped).join(" ")));
+        }
+        let decl = format!("{} :=\n{}", decl_head, ctors.join("\n"));
+        let convs = format!("definition {aux}.unwrap {params}: {applied} → {ty}\n{}\n\ndefinition {aux}.wrap {params}: {ty} → {applied}\n{}",
+                            unwraps.join("\n"), wraps.join("\n"),
+                            aux=aux, params=params, applied=applied_aux, ty=lean_ty);
+        Ok((decl, convs))
+    }
+
+    /// `inductive A ... with B ...` for all members and auxiliary types, followed by conversions and
+    /// field accessors
+    pub fn transpile_type_group(&self, group: &TypeGroup<'tcx>) -> TransResult<Option<String>> {
+        if group.members[0] != self.def_id {
+            // emitted by the first member
+            self.add_dep(group.members[0]);
+            return Ok(None)
+        }
+        let ty_params = |did| self.tcx.item_generics(did).types.iter().map(|p| p.name).collect_vec();
+        if group.members.iter().any(|&did| ty_params(did) != ty_params(self.def_id)) {
+            throw!("unimplemented: mutually recursive types with different type parameters")
+        }
+
+        let mut decls = vec![];
+        let mut accessors = vec![];
+        for &did in &group.members {
+            let name = self.name_def_id(did);
+            let adt_def = self.tcx.lookup_adt_def(did);
+            if adt_def.adt_kind() == ty::AdtKind::Struct {
+                let (decl, accs) = self.transpile_group_struct(group, &name, adt_def.struct_variant())?;
+                decls.push(decl);
+                accessors.push(accs);
+                continue
+            }
+            let generics = self.tcx.item_generics(did);
+            let applied_ty = self.mk_applied_ty(&name, generics);
+            let mut variants = vec![];
+            for variant in &adt_def.variants {
+                variants.push(match variant.ctor_kind {
+                    CtorKind::Const => format!("| {} {{}} : {}", self.mk_lean_name(variant.name), applied_ty),
+                    CtorKind::Fn => {
+                        let fields = variant.fields.iter().map(|f| self.transpile_group_ty(group, f.unsubst_ty())).try()?;
+                        format!("| {} {{}} : {}", self.mk_lean_name(variant.name),
+                                fields.chain(iter::once(applied_ty.clone())).join(" → "))
+                    }
+                    CtorKind::Fictive => {
+                        let struct_name = format!("{}.{}.struct", name, variant.name);
+                        let (decl, accs) = self.transpile_group_struct(group, &struct_name, variant)?;
+                        decls.push(decl);
+                        accessors.push(accs);
+                        format!("| {} {{}} : {} → {}", self.mk_lean_name(variant.name),
+                                self.mk_applied_ty(&struct_name, generics), applied_ty)
+                    }
+                });
+            }
+            decls.push(format!("{} :=\n{}",
+                               (&name, generics.types.iter().map(|p| format!("({} : Type₁)", p.name))).join(" "),
+                               variants.join("\n")));
+        }
+        let mut convs = vec![];
+        for &(ty, ref aux) in &group.nested {
+            let (decl, conv) = self.transpile_nested(group, ty, aux)?;
+            decls.push(decl);
+            convs.push(conv);
+        }
+        // auxiliary types nested in other ones come later
+        convs.reverse();
+        Ok(Some((format!("inductive {}", decls.join("\n\nwith ")), convs.into_iter().chain(accessors).filter(|s| !s.is_empty())).join("\n\n")))
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::iter;
 use std::ops::Deref;
 
@@ -14,6 +14,7 @@ use rustc::ty::{self, Lift, Ty};
 
 use util::*;
 use trans::TransResult;
+use trans::fun::Effects;
 use trans::krate::{self, CrateTranspiler};
 
 pub fn mk_tuple_ty<It: IntoIterator<Item=String>>(it: It) -> String {
@@ -88,6 +89,8 @@ type AssocTyRef<'tcx> = (ty::TraitRef<'tcx>, ast::Name);
 pub struct ItemTranspiler<'a, 'tcx: 'a> {
     pub sup: &'a CrateTranspiler<'a, 'tcx>,
     pub def_id: DefId,
+    // concrete instance being translated, see `CrateTranspiler::transpile_instances`
+    pub substs: Option<&'tcx Substs<'tcx>>,
 }
 
 impl<'a, 'tcx> Deref for ItemTranspiler<'a, 'tcx> {
@@ -99,7 +102,12 @@ impl<'a, 'tcx> Deref for ItemTranspiler<'a, 'tcx> {
 }
 
 impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
-    pub fn name(&self) -> String { krate::name_def_id(self.tcx, self.def_id) }
+    pub fn name(&self) -> String {
+        match self.substs {
+            Some(substs) => self.instance_name(self.def_id, substs),
+            None => krate::name_def_id(self.tcx, self.def_id),
+        }
+    }
     pub fn node_id(&self) -> NodeId {
         self.tcx.map.as_local_node_id(self.def_id).unwrap()
     }
@@ -231,15 +239,25 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
     }
 
     pub fn transpile_ty_params(&self, def_id: DefId) -> TransResult<Vec<LeanTyParam<'tcx>>> {
-        self.transpile_ty_params_with_substs(def_id, self.def_id, self.free_substs_for_item(def_id), false)
+        if self.substs.is_some() {
+            return Ok(vec![])
+        }
+        let ty_params = self.transpile_ty_params_with_substs(def_id, self.def_id, self.free_substs_for_item(def_id), false)?;
+        Ok(if self.config.monomorphize {
+            // no classes to pass
+            ty_params.into_iter().filter(|p| match *p {
+                LeanTyParam::TraitRef(..) => false,
+                _ => true,
+            }).collect()
+        } else { ty_params })
     }
 
     /// `Fn(&mut T) -> R` ~> `(R × T)`
     /// `Fn(&mut T) -> &mut S` ~> `lens T S`
     pub fn ret_ty(&self, in_tys: &[ty::Ty<'tcx>], out_ty: ty::Ty<'tcx>) -> TransResult {
-        let muts = in_tys.iter().filter_map(|i| krate::try_unwrap_mut_ref(i));
-        let out_ty = match krate::try_unwrap_mut_ref(out_ty) {
-            Some(inner) => match in_tys.first().cloned().and_then(krate::try_unwrap_mut_ref) {
+        let muts = in_tys.iter().filter_map(|i| self.try_unwrap_mut_ref(i));
+        let out_ty = match self.try_unwrap_mut_ref(out_ty) {
+            Some(inner) => match in_tys.first().cloned().and_then(|ty| self.try_unwrap_mut_ref(ty)) {
                 Some(outer) =>
                     format!("(lens {} {})", self.transpile_ty(outer)?, self.transpile_ty(inner)?),
                 None => throw!("unimplemented: returning mutable reference to argument other than the first"),
@@ -280,8 +298,14 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
     }
 
     pub fn transpile_ty(&self, ty: Ty<'tcx>) -> TransResult {
+        let mono_ty = self.mono_ty(ty);
+        if mono_ty != ty {
+            return self.transpile_ty(mono_ty)
+        }
         Ok(match ty.sty {
             ty::TypeVariants::TyBool => "bool".to_string(),
+            ty::TypeVariants::TyUint(ref ty) if self.config.bitvec_ints => format!("(bitvec {}.bits)", ty),
+            ty::TypeVariants::TyInt(ref ty) if self.config.bitvec_ints => format!("(bitvec {}.bits)", ty),
             ty::TypeVariants::TyUint(ref ty) => ty.to_string(),
             ty::TypeVariants::TyInt(ref ty) => ty.to_string(),
             ty::TypeVariants::TyChar => "char32".to_string(), // Lean already has an 8-bit `char`
@@ -295,9 +319,17 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
                 if sig.variadic {
                     throw!("unsafe: variadic function signature")
                 }
-                let inputs = try_iter!(sig.inputs.iter().map(|ty| self.transpile_ty(krate::unwrap_mut_ref(ty))));
-                format!("({})", inputs.chain(iter::once(format!("sem {}", self.ret_ty(&sig.inputs, sig.output)?))).join(" → "))
+                let inputs = try_iter!(sig.inputs.iter().map(|ty| self.transpile_ty(self.unwrap_mut_ref(ty))));
+                format!("({})", inputs.chain(iter::once(format!("{} {}", self.monad().ty, self.ret_ty(&sig.inputs, sig.output)?))).join(" → "))
             },
+            // see `alloc/pre.lean`
+            ty::TypeVariants::TyAdt(ref adt_def, _) if !self.config.heap_model && krate::is_rc(self.tcx, adt_def.did) =>
+                throw!("unimplemented: Rc without `heap_model`"),
+            ty::TypeVariants::TyAdt(ref adt_def, _) if !self.config.interior_mut_model && krate::is_cell(self.tcx, adt_def.did) =>
+                throw!("unimplemented: interior mutability without `interior_mut_model`"),
+            // see `try_unwrap_mut_ref`
+            ty::TypeVariants::TyAdt(ref adt_def, ref substs) if krate::is_refcell_guard(self.tcx, adt_def.did) =>
+                format!("(core.cell.RefCell {})", self.transpile_ty(substs.type_at(0))?),
             ty::TypeVariants::TyAdt(ref adt_def, ref substs) => format!(
                 "({})",
                 (&self.name_def_id(adt_def.did), try_iter!(substs.types().map(|ty| self.transpile_ty(ty)))).join(" ")
@@ -312,7 +344,8 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
                 self.mk_lean_name(format!("{:?}.{}", proj.trait_ref, proj.item_name))
             }
             ty::TypeVariants::TySlice(ref ty) => format!("(slice {})", self.transpile_ty(ty)?),
-            ty::TypeVariants::TyStr => "string".to_string(),
+            // a byte slice, see `is_str` in core/pre.lean
+            ty::TypeVariants::TyStr => format!("(slice {})", self.transpile_ty(self.tcx.types.u8)?),
             ty::TypeVariants::TyTrait(_) => throw!("unimplemented: trait object"),
             ty::TypeVariants::TyArray(ref ty, size) =>
                 format!("(array {} {})", self.transpile_ty(ty)?, size),
@@ -322,15 +355,29 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
             }
             ty::TypeVariants::TyClosure(def_id, ref substs) => {
                 let upvar_tys = substs.upvar_tys(def_id, self.tcx).map(|ty| self.transpile_ty(ty)).try()?;
-                format!("({})", (&self.name_def_id(def_id), upvar_tys).join(" "))
+                let name = if self.config.monomorphize { self.mono_name(def_id, substs.substs) } else { self.name_def_id(def_id) };
+                format!("({})", (&name, upvar_tys).join(" "))
             }
             ty::TypeVariants::TyNever => "empty".to_string(),
+            ty::TypeVariants::TyRawPtr(ty::TypeAndMut { ref ty, .. }) if self.config.heap_model =>
+                format!("(ptr {})", self.transpile_ty(ty)?),
             ty::TypeVariants::TyRawPtr(_) => throw!("unsafe: raw pointer"),
             ty::TypeVariants::TyAnon(..) | ty::TypeVariants::TyInfer(_) | ty::TypeVariants::TyError =>
                 unreachable!(),
         })
     }
 
+    /// `u32` ~> `'u32.bits'`
+    pub fn transpile_bits(&self, ty: Ty<'tcx>) -> TransResult {
+        Ok(match ty.sty {
+            ty::TypeVariants::TyUint(ref ty) => format!("{}.bits", ty),
+            ty::TypeVariants::TyInt(ref ty) => format!("{}.bits", ty),
+            ty::TypeVariants::TyChar => "char32.bits".to_string(),
+            ty::TypeVariants::TyBool => "1".to_string(),
+            _ => throw!("unimplemented: bit width of |{:?}", ty),
+        })
+    }
+
     fn trait_predicates(&'a self, def_id: DefId) -> impl Iterator<Item=ty::TraitPredicate<'tcx>> {
         let predicates = if let Some(trait_def_id) = self.tcx.trait_of_item(def_id) {
             // for trait items, ignore predicates on trait except for the `Self: Trait` predicate
@@ -369,24 +416,36 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
         self.trait_predicates(def_id).filter(|trait_pred| !self.is_marker_trait(trait_pred.def_id())).collect_vec().into_iter()
     }
 
+    /// `trait_ref` normalized and with all regions erased, including those bound by `for<'a>`
+    fn erase_all_regions(&self, trait_ref: ty::TraitRef<'tcx>) -> ty::TraitRef<'tcx> {
+        let trait_ref = self.normalize_trait_ref(trait_ref);
+        self.tcx.fold_regions(&trait_ref, &mut false, |_, _| self.tcx.mk_region(ty::ReErased))
+    }
+
+    /// Whether the bound `cur` or one of its supertraits or associated type bounds is `target`
+    fn provides_trait_ref(&self, cur: ty::TraitRef<'tcx>, target: ty::TraitRef<'tcx>) -> bool {
+        self.erase_all_regions(cur) == target || self.tcx.item_predicates(cur.def_id).predicates.into_iter().any(|pred| match pred {
+            ty::Predicate::Trait(ref trait_pred) if trait_pred.def_id() != cur.def_id => {
+                let new = trait_pred.0.trait_ref.subst(self.tcx, cur.substs);
+                self.provides_trait_ref(new, target)
+            }
+            _ => false,
+        })
+    }
+
+    /// Instance parameter of the current item for `target`, which may be a supertrait of a bound, a bound on
+    /// a projection like `I::Item: Ord`, an impl-level where-clause or a higher-ranked bound
     fn find_local_trait_impl(&self, target: ty::TraitRef<'tcx>) -> TransResult {
-        fn rec<'a, 'tcx>(tcx: ty::TyCtxt<'a, 'tcx, 'tcx>, cur: ty::TraitRef<'tcx>, target: ty::TraitRef<'tcx>) -> bool {
-            tcx.erase_regions(&cur) == tcx.erase_regions(&target) || tcx.item_predicates(cur.def_id).predicates.into_iter().any(|pred| match pred {
-                ty::Predicate::Trait(ref trait_pred) if trait_pred.def_id() != cur.def_id => {
-                    let new = trait_pred.0.trait_ref.subst(tcx, cur.substs);
-                    rec(tcx, new, target)
-                }
-                _ => false,
-            })
-        }
+        let erased_target = self.erase_all_regions(target);
         for ty_param in self.transpile_ty_params(self.def_id)? {
             if let LeanTyParam::TraitRef(_, _, trait_ref) = ty_param {
-                if rec(self.tcx, trait_ref, target) {
+                if self.provides_trait_ref(trait_ref, erased_target) {
                     return Ok(self.mk_lean_name(self.transpile_trait_ref_no_assoc_tys(trait_ref)?))
                 }
             }
         }
-        Err(format!("error: could not find local instance |{}", self.transpile_trait_ref_no_assoc_tys(target)?))
+        Err(format!("error: could not find local instance |{} for bound `{}: {}`",
+                    self.transpile_trait_ref_no_assoc_tys(target)?, target.self_ty(), target))
     }
 
     // ugh
@@ -432,6 +491,11 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
             Vtable::VtableParam(_) => TraitImplLookup::Dynamic { param: {
                 self.find_local_trait_impl(trait_ref)?
             }},
+            Vtable::VtableClosure(ref data) if self.config.monomorphize => {
+                TraitImplLookup::Dynamic {
+                    param: format!("{}.inst", self.mono_name(data.closure_def_id, data.substs.substs)),
+                }
+            },
             Vtable::VtableClosure(data) => {
                 TraitImplLookup::Dynamic {
                     param: format!("(@{}.inst {})", self.name_def_id(data.closure_def_id),
@@ -545,12 +609,34 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
     }
 
     fn transpile_static(&self) -> TransResult {
-        Ok(format!("definition {} : sem {} :=\n{}",
-                   krate::name_def_id(self.tcx, self.def_id),
-                   self.transpile_ty(self.tcx.item_type(self.def_id))?,
+        let name = krate::name_def_id(self.tcx, self.def_id);
+        let ty = self.transpile_ty(self.tcx.item_type(self.def_id))?;
+        if self.config.const_eval {
+            match self.transpile_evaluated_static(&name, &ty) {
+                Ok(def) => return Ok(def),
+                // fall back to the MIR if the evaluator gives up
+                Err(msg) => println!("{}: falling back to MIR after {}", name, msg),
+            }
+        }
+        Ok(format!("definition {} : {} {} :=\n{}", name, self.monad().ty, ty,
                    ::trans::fun::FnTranspiler::new(self, &*self.tcx.item_mir(self.def_id)).transpile_mir()?))
     }
 
+    /// `static mut X: T` ~> field `X : T` of the global state record, initialized by `definition X`
+    pub fn transpile_globals(&self, statics: &[DefId]) -> TransResult {
+        let ty = ::trans::fun::globals_ty(self.tcx);
+        let fields = statics.iter().map(|&def_id| -> TransResult {
+            Ok(format!("({} : {})", ::trans::fun::globals_field(self.tcx, def_id),
+                       self.transpile_ty(self.tcx.item_type(def_id))?))
+        }).try()?.join("\n");
+        let inits = statics.iter().map(|&def_id| {
+            format!("{} {} ← {};\n", self.monad().bind, ::trans::fun::globals_field(self.tcx, def_id), self.name_def_id(def_id))
+        }).join("");
+        let vals = statics.iter().map(|&def_id| ::trans::fun::globals_field(self.tcx, def_id));
+        Ok(format!("structure {ty} := mk ::\n{}\n\ndefinition {ty}.init : {m} {ty} :=\n{}{ret} {}",
+                   fields, inits, ((ty.clone() + ".mk"), vals).join(" "), ty=ty, m=self.monad().ty, ret=self.monad().ret))
+    }
+
     fn transpile_trait(&self, name: &str) -> TransResult {
         let ty_params = self.transpile_ty_params(self.def_id)?.into_iter().filter(|p| match *p {
             LeanTyParam::TraitRef(_, _, ref trait_ref) => trait_ref.def_id != self.def_id,
@@ -566,16 +652,14 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
             format!(" extends {}", supertraits.iter().map(|s| s.0.clone()).join(", "))
         };
 
-        let only_path = format!("traits.\"{}\".only", name);
-        let only: Option<HashSet<_>> = self.config.config.lookup(&only_path).map(|only| ::toml_value_as_str_array(only).into_iter().collect());
         let items = self.tcx.associated_items(self.def_id).try_filter_map(|item| Ok(match item.kind {
             ty::AssociatedKind::Type => None,
             ty::AssociatedKind::Method => {
                 // TODO: allow overriding default methods
-                if self.tcx.provided_trait_methods(self.def_id).iter().any(|m| m.name == item.name) || only.iter().any(|only| !only.contains(&*item.name.as_str())) {
+                if self.tcx.provided_trait_methods(self.def_id).iter().any(|m| m.name == item.name) || !self.is_class_method(self.def_id, item.name) {
                     None
                 } else {
-                    let ty_params = ItemTranspiler { sup: self.sup, def_id: item.def_id }.transpile_ty_params_with_substs(item.def_id, self.def_id, self.free_substs_for_item(item.def_id), false)?;
+                    let ty_params = ItemTranspiler { sup: self.sup, def_id: item.def_id, substs: None }.transpile_ty_params_with_substs(item.def_id, self.def_id, self.free_substs_for_item(item.def_id), false)?;
                     self.add_dep(item.def_id);
                     let pi = if ty_params.is_empty() { "".to_string() } else {
                         format!("Π {}, ", ty_params.iter().map(LeanTyParam::to_string).join(" "))
@@ -587,7 +671,7 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
             ty::AssociatedKind::Const =>
                 throw!("unimplemented: const trait items"),
         }))?.collect_vec();
-        Ok(format!("structure {}{}{}{}",
+        Ok(format!("structure {}{}{}{}{}",
                    (self.name_def_id(self.def_id) + " [class]", ty_params).join(" "),
                    extends,
                    if items.is_empty() { " := mk".to_string() }
@@ -596,7 +680,8 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
                        format!("\n\nattribute [coercion] {}", supertraits.iter().map(|s| {
                            format!("{}.to_{}", name, self.tcx.item_name(s.1.def_id))
                        }).join(" "))
-                   }))
+                   },
+                   self.transpile_trait_laws(name)?.map_or("".to_string(), |laws| format!("\n\n{}", laws))))
     }
 
     fn transpile_trait_impl(&self) -> TransResult {
@@ -610,14 +695,17 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
                 .try()
         })?;
 
-        let only_path = format!("traits.\"{}\".only", &self.name_def_id(trait_ref.def_id));
-        let only: Option<HashSet<_>> = self.config.config.lookup(&only_path).map(|only| ::toml_value_as_str_array(only).into_iter().collect());
         let items = self.tcx.associated_items(self.def_id).try_filter_map(|item| Ok(match item.kind {
             ty::AssociatedKind::Type =>
                 None,
             ty::AssociatedKind::Method => {
-                if only.iter().any(|only| !only.contains(&*item.name.as_str())) {
-                    None // method ignored in config
+                if !self.is_class_method(trait_ref.def_id, item.name) {
+                    None // method pruned or ignored in config
+                } else if self.is_derived_debug(self.def_id) {
+                    None
+                } else if self.effects(item.def_id) != Effects::default() {
+                    // class methods have a fixed signature
+                    throw!("unimplemented: trait method using implicit state |{:?}", self.effects(item.def_id))
                 } else {
                     Some(format!("{} := @{}", self.mk_lean_name(item.name), (self.name_def_id(item.def_id), ty_params.iter().map(|p| p.name())).join(" ")))
                 }
@@ -628,12 +716,18 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
 
         let mut bound_assoc_tys = HashMap::new();
         self.free_assoc_tys(trait_ref, &mut bound_assoc_tys)?;
-        Ok(format!("definition {} := ⦃\n  {}\n⦄",
+        Ok(format!("definition {} := ⦃\n  {}\n⦄{}",
                    (self.name() + " [instance]", ty_params.iter().map(LeanTyParam::to_string)).join(" "),
-                   (self.transpile_trait_ref(trait_ref, &mut bound_assoc_tys)?, supertrait_impls.into_iter().chain(items)).join(",\n  ")))
+                   (self.transpile_trait_ref(trait_ref, &mut bound_assoc_tys)?, supertrait_impls.into_iter().chain(items)).join(",\n  "),
+                   self.transpile_impl_laws(&ty_params)?.map_or("".to_string(), |laws| format!("\n\n{}", laws))))
     }
 
     fn transpile_fn(&self, name: String) -> TransResult {
+        if self.substs.is_none() {
+            if let Some(def) = self.pure_fn(self.def_id) {
+                return Ok(def)
+            }
+        }
         ::trans::fun::FnTranspiler::new(self, &*self.tcx.item_mir(self.def_id)).transpile_fn(name)
     }
 
@@ -647,24 +741,35 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
             Node::NodeItem(item) => match item.node {
                 Item_::ItemExternCrate(_) | Item_::ItemUse(_) | Item_::ItemMod(_)
                 | Item_::ItemForeignMod(_) => return Ok(None),
+                // initial value, see `transpile_globals`
+                Item_::ItemStatic(_, hir::Mutability::MutMutable, _) if self.config.global_state =>
+                    self.transpile_static()?,
                 Item_::ItemStatic(_, hir::Mutability::MutMutable, _) =>
                     throw!("unsafe: mutable static |{:?}", name),
                 Item_::ItemStatic(_, hir::Mutability::MutImmutable, _) | Item_::ItemConst(..) =>
                     self.transpile_static()?,
+                Item_::ItemEnum(..) | Item_::ItemStruct(..) if self.type_group(self.def_id).is_some() =>
+                    return self.transpile_type_group(&self.type_group(self.def_id).unwrap()),
                 Item_::ItemEnum(..) =>
                     match self.tcx.item_type(self.def_id).sty {
-                        ty::TypeVariants::TyAdt(ref adt_def, _) =>
-                            self.transpile_enum(&name, adt_def)?,
+                        ty::TypeVariants::TyAdt(adt_def, _) => {
+                            let def = self.transpile_enum(&name, adt_def)?;
+                            (def, self.transpile_derived_instances(adt_def)).join("\n\n")
+                        }
                         _ => unreachable!(),
                     },
                 Item_::ItemStruct(..) =>
                     match self.tcx.item_type(self.def_id).sty {
-                        ty::TypeVariants::TyAdt(ref adt_def, _) =>
-                            self.transpile_struct("", adt_def.struct_variant())?,
+                        ty::TypeVariants::TyAdt(adt_def, _) => {
+                            let def = self.transpile_struct("", adt_def.struct_variant())?;
+                            let inv = ::trans::spec::or_comment(&format!("{}.inv", name), self.transpile_struct_invariant());
+                            (def, inv.into_iter().chain(self.transpile_derived_instances(adt_def))).join("\n\n")
+                        }
                         _ => unreachable!(),
                     },
                 Item_::ItemTrait(..) => {
-                    if self.is_marker_trait(self.def_id) {
+                    // all calls are resolved statically when monomorphizing
+                    if self.is_marker_trait(self.def_id) || self.config.monomorphize {
                         return Ok(None)
                     }
                     self.transpile_trait(&name)?
@@ -672,6 +777,15 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
                 Item_::ItemDefaultImpl(..) => return Ok(None),
                 Item_::ItemImpl(..) => {
                     if let Some(trait_ref) = self.tcx.impl_trait_ref(self.def_id) {
+                        if self.config.monomorphize && !self.is_marker_trait(trait_ref.def_id) {
+                            // instances of other crates' classes are still needed by their generic functions
+                            if trait_ref.def_id.is_local() {
+                                return Ok(None)
+                            }
+                            if !self.tcx.item_generics(self.def_id).types.is_empty() {
+                                throw!("unimplemented: generic impl of other crate's trait when monomorphizing")
+                            }
+                        }
                         if !self.is_marker_trait(trait_ref.def_id) {
                             return Ok(Some(self.transpile_trait_impl()?))
                         }
@@ -684,6 +798,7 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
                             self.transpile_ty(self.tcx.item_type(self.def_id))?),
                 Item_::ItemFn(..) =>
                     self.transpile_fn(name)?,
+                Item_::ItemUnion(..) if self.config.layout_model => self.transpile_union()?,
                 Item_::ItemUnion(..) => throw!("unsafe: union type"),
             },
             Node::NodeExpr(_) => // top-level expr? closure!
@@ -701,7 +816,13 @@ impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
                         throw!("unimplemented: overriding default method |{}", self.name_def_id(self.def_id))
                     }
                 }
-                self.transpile_fn(name)?
+                if self.is_derived_debug(impl_def_id) {
+                    return Ok(None)
+                }
+                match self.transpile_derived_method(impl_def_id)? {
+                    Some(def) => def,
+                    None => self.transpile_fn(name)?,
+                }
             }
             Node::NodeTraitItem(_) | Node::NodeVariant(_) | Node::NodeStructCtor(_)
             | Node::NodeImplItem(&hir::ImplItem { node: hir::ImplItemKind::Type(..), .. }) =>
@@ -1,5 +1,9 @@
 use std::cell::RefCell;
 use std::collections::{HashMap, HashSet, BTreeSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::rc::Rc;
 
 use itertools::Itertools;
 use petgraph::graph::{self, Graph};
@@ -7,11 +11,16 @@ use regex::Regex;
 use toml;
 
 use rustc::hir;
-use rustc::hir::def_id::DefId;
+use rustc::hir::def_id::{CrateNum, DefId, CRATE_DEF_INDEX};
 use rustc::ty::{self, Ty, TyCtxt};
+use rustc::ty::subst::Substs;
 
 use item_path;
+use trans::cost::CostModel;
+use trans::fun::{Effects, is_static_mut};
+use trans::group::TypeGroup;
 use trans::item::ItemTranspiler;
+use trans::monad::Monad;
 
 lazy_static! {
     static ref LEAN_ID: Regex = Regex::new(r"^(_|[:alpha:])(_|'|[:alpha:]|\d)*$").unwrap();
@@ -36,16 +45,53 @@ pub fn mk_lean_name_from_parts<'a, It, S>(parts: It) -> String
     }).join(".")
 }
 
-pub fn try_unwrap_mut_ref<'tcx>(ty: Ty<'tcx>) -> Option<Ty<'tcx>> {
-    match ty.sty {
-        ty::TypeVariants::TyRef(_, ty::TypeAndMut { mutbl: hir::Mutability::MutMutable, ty }) =>
-            Some(ty),
-        _ => None
+/// Escapes a Rust string for use as a Lean string literal
+/// `a"b` ~> `"a\"b"`
+pub fn mk_lean_string_lit(s: &str) -> String {
+    format!("\"{}\"", s.replace("\\", "\\\\").replace("\"", "\\\"").replace("\n", "\\n"))
+}
+
+/// Whether a crate config selects `int_repr = "bitvec"`
+pub fn bitvec_ints(config: &toml::Value) -> bool {
+    match config.lookup("int_repr").map(|repr| repr.as_str().expect("'int_repr' must be a string")) {
+        None | Some("nat") => false,
+        Some("bitvec") => true,
+        Some(repr) => panic!("unknown 'int_repr' {}, expected \"nat\" or \"bitvec\"", repr),
+    }
+}
+
+/// Whether the crate is translated with `int_repr = "bitvec"`, according to its config in `thys`
+pub fn config_bitvec_ints(crate_name: &str) -> bool {
+    let mut config = String::new();
+    match File::open(Path::new("thys").join(crate_name).join("config.toml")) {
+        Ok(mut file) => {
+            file.read_to_string(&mut config).unwrap();
+            bitvec_ints(&config.parse().unwrap())
+        }
+        Err(_) => false,
+    }
+}
+
+/// `core::cell::Ref` and `core::cell::RefMut`
+pub fn is_refcell_guard(tcx: TyCtxt, def_id: DefId) -> bool {
+    let name = name_def_id(tcx, def_id);
+    name == "core.cell.Ref" || name == "core.cell.RefMut"
+}
+
+/// `Rc`, `Arc` and their `Weak` handles, see `alloc/pre.lean`
+pub fn is_rc(tcx: TyCtxt, def_id: DefId) -> bool {
+    match &name_def_id(tcx, def_id)[..] {
+        "alloc.rc.Rc" | "alloc.rc.Weak" | "alloc.arc.Arc" | "alloc.arc.Weak" => true,
+        _ => false,
     }
 }
 
-pub fn unwrap_mut_ref<'tcx>(ty: Ty<'tcx>) -> Ty<'tcx> {
-    try_unwrap_mut_ref(ty).unwrap_or(ty)
+/// `core::cell::{UnsafeCell, Cell, RefCell}` and the `RefCell` guards, see `interior_mut_model`
+pub fn is_cell(tcx: TyCtxt, def_id: DefId) -> bool {
+    Some(def_id) == tcx.lang_items.unsafe_cell_type() || is_refcell_guard(tcx, def_id) || match &name_def_id(tcx, def_id)[..] {
+        "core.cell.Cell" | "core.cell.RefCell" => true,
+        _ => false,
+    }
 }
 
 pub fn name_def_id(tcx: TyCtxt, def_id: DefId) -> String {
@@ -59,11 +105,39 @@ pub fn name_def_id(tcx: TyCtxt, def_id: DefId) -> String {
 pub struct Config<'a> {
     pub ignored: Regex, // cache at least this one
     fail: Regex,
+    // label every panic with its reason (see `panic_reason` in sem.lean), kept by the default `psem` monad
+    pub panic_reasons: bool,
+    // debug (checked) vs. release (wrapping) integer arithmetic
+    pub overflow_checks: bool,
+    // represent fixed-width integers as bit vectors instead of `nat`/`int`
+    pub bitvec_ints: bool,
+    // evaluate consts and statics with rustc instead of translating their MIR
+    pub const_eval: bool,
+    // evaluated arrays longer than this are emitted as separate list definitions
+    pub const_table_threshold: usize,
+    // pass `&Cell`/`&RefCell` and `RefCell` guards like `&mut`, see `try_unwrap_mut_ref`
+    pub interior_mut_model: bool,
+    // translate raw pointers into an explicit heap threaded through heap-using functions
+    pub heap_model: bool,
+    // gather mutable statics into a record threaded through functions accessing them
+    pub global_state: bool,
+    // model unions and `transmute` via byte encodings derived from rustc's layout
+    pub layout_model: bool,
+    // emit concrete instances of generic functions instead of passing type class dictionaries
+    pub monomorphize: bool,
+    // restrict trait classes and their instances to methods called dynamically
+    pub prune_trait_methods: bool,
+    // vocabulary of the semantics monad, see `[monad]`
+    pub monad: Monad,
+    // costs charged in addition to one step per call, see `[cost]`
+    pub cost: CostModel,
     pub config: &'a toml::Value,
 }
 
 impl<'a> Config<'a> {
-    fn new(config: &'a toml::Value) -> Config {
+    fn new(tcx: TyCtxt, config: &'a toml::Value) -> Config<'a> {
+        let opts = &tcx.sess.opts;
+        let panic_reasons = config.lookup("panic_reasons").and_then(toml::Value::as_bool).unwrap_or(false);
         Config {
             ignored: match config.lookup("ignore") {
                 Some(ignored) => Regex::new(&format!("^({})$", ::toml_value_as_str_array(ignored).into_iter().join("|"))).unwrap(),
@@ -73,6 +147,23 @@ impl<'a> Config<'a> {
                 Some(ignored) => Regex::new(&format!("^({})$", ::toml_value_as_str_array(ignored).into_iter().join("|"))).unwrap(),
                 None => Regex::new("^NOPE$").unwrap(),
             },
+            panic_reasons: panic_reasons,
+            // same default as rustc's MIR building
+            overflow_checks: config.lookup("overflow_checks").and_then(toml::Value::as_bool).unwrap_or_else(|| {
+                opts.debugging_opts.force_overflow_checks.unwrap_or(opts.debug_assertions)
+            }),
+            bitvec_ints: bitvec_ints(config),
+            const_eval: config.lookup("const_eval").and_then(toml::Value::as_bool).unwrap_or(false),
+            const_table_threshold: config.lookup("const_table_threshold").and_then(toml::Value::as_integer)
+                .map_or(16, |n| n as usize),
+            interior_mut_model: config.lookup("interior_mut_model").and_then(toml::Value::as_bool).unwrap_or(false),
+            heap_model: config.lookup("heap_model").and_then(toml::Value::as_bool).unwrap_or(false),
+            global_state: config.lookup("global_state").and_then(toml::Value::as_bool).unwrap_or(false),
+            layout_model: config.lookup("layout_model").and_then(toml::Value::as_bool).unwrap_or(false),
+            monomorphize: config.lookup("monomorphize").and_then(toml::Value::as_bool).unwrap_or(false),
+            prune_trait_methods: config.lookup("prune_trait_methods").and_then(toml::Value::as_bool).unwrap_or(false),
+            monad: Monad::new(config, panic_reasons),
+            cost: CostModel::new(config, tcx.sess.opts.crate_name.as_ref().unwrap()),
             config: config,
         }
     }
@@ -115,6 +206,21 @@ pub struct CrateTranspiler<'a, 'tcx: 'a> {
     pub tcx: TyCtxt<'a, 'tcx, 'tcx>,
     pub config: Config<'a>,
     pub deps: RefCell<Deps>,
+    // cache of `effects`
+    pub effects_cache: RefCell<HashMap<DefId, Effects>>,
+    // cache of `type_group`
+    pub type_groups: RefCell<HashMap<DefId, Option<Rc<TypeGroup<'tcx>>>>>,
+    // trait methods called through class instances, see `collect_dyn_methods`
+    pub dyn_methods: HashSet<DefId>,
+    // requested instances of generic items by name and the items to retranslate, see `transpile_instances`
+    pub mono_instances: RefCell<HashMap<DefId, Vec<(String, &'tcx Substs<'tcx>)>>>,
+    pub mono_stale: RefCell<HashSet<DefId>>,
+    // ghost functions by Rust name, see `collect_spec_fns`
+    pub spec_fns: HashMap<String, Vec<DefId>>,
+    // cache of `pure_fn`
+    pub pure_fns: RefCell<HashMap<DefId, Option<String>>>,
+    // cache of `crate_bitvec_ints`
+    crate_bitvec_ints: RefCell<HashMap<CrateNum, bool>>,
     trans_results: HashMap<DefId, Result<Option<String>, String>>,
 }
 
@@ -124,7 +230,15 @@ impl<'a, 'tcx> CrateTranspiler<'a, 'tcx> {
             tcx: tcx,
             trans_results: HashMap::new(),
             deps: Default::default(),
-            config: Config::new(config),
+            effects_cache: Default::default(),
+            type_groups: Default::default(),
+            dyn_methods: HashSet::new(),
+            mono_instances: Default::default(),
+            mono_stale: Default::default(),
+            spec_fns: HashMap::new(),
+            pure_fns: Default::default(),
+            crate_bitvec_ints: Default::default(),
+            config: Config::new(tcx, config),
         }
     }
 
@@ -132,6 +246,68 @@ impl<'a, 'tcx> CrateTranspiler<'a, 'tcx> {
         mk_lean_name_from_parts(&[s.to_string()])
     }
 
+    /// The configured vocabulary; function bodies may instead be translated totally, see `FnTranspiler::monad`
+    pub fn monad(&self) -> &Monad {
+        &self.config.monad
+    }
+
+    /// Whether the type contains an `UnsafeCell` (e.g. in a `Cell` or `RefCell`) that is not behind a
+    /// pointer and thus can be mutated through `&`. Always false without `interior_mut_model`.
+    pub fn is_interior_mut(&self, ty: Ty<'tcx>) -> bool {
+        if !self.config.interior_mut_model {
+            return false
+        }
+        match ty.sty {
+            ty::TypeVariants::TyAdt(adt_def, _) if Some(adt_def.did) == self.tcx.lang_items.unsafe_cell_type() => true,
+            // points to a `RefCell`, see `try_unwrap_mut_ref`
+            ty::TypeVariants::TyAdt(adt_def, _) if is_refcell_guard(self.tcx, adt_def.did) => true,
+            ty::TypeVariants::TyAdt(adt_def, substs) =>
+                adt_def.all_fields().any(|field| self.is_interior_mut(field.ty(self.tcx, substs))),
+            ty::TypeVariants::TyTuple(tys) => tys.iter().any(|ty| self.is_interior_mut(ty)),
+            ty::TypeVariants::TyArray(ty, _) | ty::TypeVariants::TySlice(ty) => self.is_interior_mut(ty),
+            ty::TypeVariants::TyClosure(def_id, ref substs) =>
+                substs.upvar_tys(def_id, self.tcx).any(|ty| self.is_interior_mut(ty)),
+            _ => false,
+        }
+    }
+
+    /// References whose target may be mutated are passed and returned by value:
+    /// `&mut T` ~> `T`, `&Cell<T>` ~> `Cell<T>`, `Ref<'a, T>` ~> `Ref<'a, T>` (i.e. the `RefCell`)
+    pub fn try_unwrap_mut_ref(&self, ty: Ty<'tcx>) -> Option<Ty<'tcx>> {
+        match ty.sty {
+            ty::TypeVariants::TyRef(_, ty::TypeAndMut { mutbl: hir::Mutability::MutMutable, ty }) =>
+                Some(ty),
+            ty::TypeVariants::TyRef(_, ty::TypeAndMut { mutbl: hir::Mutability::MutImmutable, ty })
+                if self.is_interior_mut(ty) => Some(ty),
+            ty::TypeVariants::TyAdt(adt_def, _) if self.config.interior_mut_model && is_refcell_guard(self.tcx, adt_def.did) =>
+                Some(ty),
+            _ => None
+        }
+    }
+
+    pub fn unwrap_mut_ref(&self, ty: Ty<'tcx>) -> Ty<'tcx> {
+        self.try_unwrap_mut_ref(ty).unwrap_or(ty)
+    }
+
+    /// Gathers all translated mutable statics into the global state record, which is stored as the
+    /// translation of the crate root
+    pub fn transpile_globals(&mut self) {
+        if !self.config.global_state {
+            return
+        }
+        let mut statics = self.trans_results.keys().cloned().filter(|&def_id| is_static_mut(self.tcx, def_id)).collect_vec();
+        if statics.is_empty() {
+            return
+        }
+        statics.sort_by_key(|&def_id| name_def_id(self.tcx, def_id));
+        let root = DefId::local(CRATE_DEF_INDEX);
+        let res = match statics.iter().find(|def_id| self.trans_results[def_id].is_err()) {
+            Some(&def_id) => Err(format!("unimplemented: global state |with failed {}", name_def_id(self.tcx, def_id))),
+            None => ItemTranspiler { sup: self, def_id: root, substs: None }.transpile_globals(&statics).map(Some),
+        };
+        self.trans_results.insert(root, res);
+    }
+
     pub fn destruct(self) -> (HashMap<DefId, Result<Option<String>, String>>, Deps) {
         (self.trans_results, self.deps.into_inner())
     }
@@ -145,6 +321,13 @@ impl<'a, 'tcx> CrateTranspiler<'a, 'tcx> {
         }
     }
 
+    /// Whether another crate is translated with `int_repr = "bitvec"`
+    pub fn crate_bitvec_ints(&self, krate: CrateNum) -> bool {
+        *self.crate_bitvec_ints.borrow_mut().entry(krate).or_insert_with(|| {
+            config_bitvec_ints(&self.tcx.sess.cstore.crate_name(krate))
+        })
+    }
+
     pub fn is_recursive(&self, _def_id: DefId) -> bool {
         //let idx = self.deps.borrow_mut().get_def_idx(def_id);
         // look for self-loop
@@ -156,7 +339,8 @@ impl<'a, 'tcx> CrateTranspiler<'a, 'tcx> {
     pub fn transpile(&mut self, def_id: DefId, filter: &HashSet<DefId>) {
         let name = name_def_id(self.tcx, def_id);
 
-        if self.trans_results.contains_key(&def_id) || !filter.contains(&def_id) {
+        let stale = self.mono_stale.borrow_mut().remove(&def_id);
+        if (self.trans_results.contains_key(&def_id) && !stale) || !filter.contains(&def_id) {
             return
         }
 
@@ -172,10 +356,16 @@ impl<'a, 'tcx> CrateTranspiler<'a, 'tcx> {
 
         println!("{}...", name);
         self.deps.borrow_mut().get_def_idx(def_id); // add to dependency graph
-        let res = self.config.config.lookup(&format!("replace.\"{}\"", name)).map(|res| Ok(Some(res.as_str().unwrap().to_string())));
-        let res = res.unwrap_or_else(|| {
-            ItemTranspiler { sup: self, def_id: def_id }.transpile_def_id()
+        let res = self.config.config.lookup(&format!("replace.\"{}\"", name)).map(|res| {
+            // a replaced instance still has to come after its trait
+            if let Some(hir::map::NodeItem(&hir::Item { node: hir::Item_::ItemImpl(..), .. })) = self.tcx.map.get_if_local(def_id) {
+                if let Some(trait_ref) = self.tcx.impl_trait_ref(def_id) {
+                    self.add_dep(trait_ref.def_id, def_id);
+                }
+            }
+            Ok(Some(res.as_str().unwrap().to_string()))
         });
+        let res = res.unwrap_or_else(|| self.transpile_instances(def_id));
         if let Err(ref msg) = res {
             if self.config.config.lookup("targets").is_some() {
                 panic!("{}", msg)
@@ -190,5 +380,10 @@ impl<'a, 'tcx> CrateTranspiler<'a, 'tcx> {
         for dep in new_deps {
             self.transpile(dep, filter)
         }
+        // items with newly requested instances
+        let stale = self.mono_stale.borrow().iter().cloned().collect_vec();
+        for dep in stale {
+            self.transpile(dep, filter)
+        }
     }
 }
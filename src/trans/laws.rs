@@ -0,0 +1,127 @@
+use itertools::Itertools;
+
+use rustc::hir::def_id::DefId;
+
+use util::*;
+use trans::item::{ItemTranspiler, LeanTyParam};
+use trans::krate;
+use trans::monad::Monad;
+use trans::TransResult;
+
+/// Laws of standard traits, stated on their homogeneous instances (`PartialEq Self Self`)
+fn builtin_laws(trait_name: &str, monad: &Monad) -> Option<Vec<String>> {
+    let (ret, term) = (&monad.returns, &monad.terminates);
+    Some(match trait_name {
+        "core.cmp.PartialEq" => vec![
+            format!("(eq_total : ∀ x y : Self, {term} (core.cmp.PartialEq.eq x y))", term=term),
+            format!("(eq_symm : ∀ x y : Self, {ret} tt (core.cmp.PartialEq.eq x y) → {ret} tt (core.cmp.PartialEq.eq y x))", ret=ret),
+            format!("(eq_trans : ∀ x y z : Self, {ret} tt (core.cmp.PartialEq.eq x y) → {ret} tt (core.cmp.PartialEq.eq y z) →
+  {ret} tt (core.cmp.PartialEq.eq x z))", ret=ret),
+        ],
+        "core.cmp.Eq" => vec![
+            format!("(eq_refl : ∀ x : Self, {ret} tt (core.cmp.PartialEq.eq x x))", ret=ret),
+        ],
+        "core.cmp.PartialOrd" => vec![
+            format!("(partial_cmp_total : ∀ x y : Self, {term} (core.cmp.PartialOrd.partial_cmp x y))", term=term),
+            format!("(partial_cmp_eq : ∀ x y : Self,
+  {ret} (core.option.Option.Some core.cmp.Ordering.Equal) (core.cmp.PartialOrd.partial_cmp x y) ↔
+  {ret} tt (core.cmp.PartialEq.eq x y))", ret=ret),
+            format!("(partial_cmp_antisymm : ∀ x y : Self,
+  {ret} (core.option.Option.Some core.cmp.Ordering.Less) (core.cmp.PartialOrd.partial_cmp x y) ↔
+  {ret} (core.option.Option.Some core.cmp.Ordering.Greater) (core.cmp.PartialOrd.partial_cmp y x))", ret=ret),
+            format!("(partial_cmp_trans : ∀ x y z : Self,
+  {ret} (core.option.Option.Some core.cmp.Ordering.Less) (core.cmp.PartialOrd.partial_cmp x y) →
+  {ret} (core.option.Option.Some core.cmp.Ordering.Less) (core.cmp.PartialOrd.partial_cmp y z) →
+  {ret} (core.option.Option.Some core.cmp.Ordering.Less) (core.cmp.PartialOrd.partial_cmp x z))", ret=ret),
+        ],
+        "core.cmp.Ord" => vec![
+            format!("(cmp_total : ∀ x y : Self, {term} (core.cmp.Ord.cmp x y))", term=term),
+            format!("(cmp_eq : ∀ x y : Self, {ret} core.cmp.Ordering.Equal (core.cmp.Ord.cmp x y) ↔ {ret} tt (core.cmp.PartialEq.eq x y))", ret=ret),
+            format!("(cmp_antisymm : ∀ x y : Self,
+  {ret} core.cmp.Ordering.Less (core.cmp.Ord.cmp x y) ↔ {ret} core.cmp.Ordering.Greater (core.cmp.Ord.cmp y x))", ret=ret),
+            format!("(cmp_trans : ∀ x y z : Self,
+  {ret} core.cmp.Ordering.Less (core.cmp.Ord.cmp x y) → {ret} core.cmp.Ordering.Less (core.cmp.Ord.cmp y z) →
+  {ret} core.cmp.Ordering.Less (core.cmp.Ord.cmp x z))", ret=ret),
+            format!("(cmp_partial_cmp : ∀ (x y : Self) o,
+  {ret} o (core.cmp.Ord.cmp x y) → {ret} (core.option.Option.Some o) (core.cmp.PartialOrd.partial_cmp x y))", ret=ret),
+        ],
+        "core.clone.Clone" => vec![
+            format!("(clone_eq : ∀ x : Self, {ret} x (core.clone.Clone.clone x))", ret=ret),
+        ],
+        _ => return None,
+    })
+}
+
+impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
+    /// Law fields of a trait selected in the config by `traits."<trait>".laws`, which is either `true` for
+    /// the built-in laws of a standard trait or a list of fields
+    fn trait_laws(&self, trait_name: &str) -> TransResult<Option<Vec<String>>> {
+        let laws = match self.config.config.lookup(&format!("traits.\"{}\".laws", trait_name)) {
+            Some(laws) => laws,
+            None => return Ok(None),
+        };
+        Ok(match laws.as_bool() {
+            Some(false) => None,
+            Some(true) => match builtin_laws(trait_name, self.monad()) {
+                Some(laws) => Some(laws),
+                None => throw!("unimplemented: no built-in laws for |{}", trait_name),
+            },
+            None => Some(::toml_value_as_str_array(laws).into_iter().map(ToString::to_string).collect()),
+        })
+    }
+
+    /// `Trait Self Self ...`
+    fn homogeneous_instance(&self, trait_did: DefId, self_ty: &str) -> TransResult {
+        let ty_params = self.transpile_ty_params(trait_did)?;
+        let mut n = 0;
+        for p in &ty_params {
+            match *p {
+                LeanTyParam::RustTyParam(_) => n += 1,
+                LeanTyParam::AssocTy(_) => throw!("unimplemented: laws of trait with associated types"),
+                LeanTyParam::TraitRef(..) => {}
+            }
+        }
+        Ok((krate::name_def_id(self.tcx, trait_did), (0..n).map(|_| self_ty)).join(" "))
+    }
+
+    /// Companion class `Trait.laws` of the trait being translated
+    pub fn transpile_trait_laws(&self, name: &str) -> TransResult<Option<String>> {
+        let laws = match self.trait_laws(name)? {
+            Some(laws) => laws,
+            None => return Ok(None),
+        };
+        let inst = self.homogeneous_instance(self.def_id, "Self")?;
+        Ok(Some(format!("structure {}.laws [class] (Self : Type₁) [{} : {}] : Prop :=\n{}",
+                        name, self.mk_lean_name(&inst), inst, laws.join("\n"))))
+    }
+
+    /// Proof obligation `Trait.laws Self` of the trait impl being translated, assuming the laws of the
+    /// impl's own bounds
+    pub fn transpile_impl_laws(&self, ty_params: &[LeanTyParam<'tcx>]) -> TransResult<Option<String>> {
+        let trait_ref = self.tcx.impl_trait_ref(self.def_id).unwrap();
+        let trait_name = krate::name_def_id(self.tcx, trait_ref.def_id);
+        if self.trait_laws(&trait_name)?.is_none() {
+            return Ok(None)
+        }
+        let self_ty = trait_ref.self_ty();
+        if trait_ref.substs.types().any(|ty| ty != self_ty) {
+            // laws only cover homogeneous instances
+            return Ok(None)
+        }
+        let assumptions = ty_params.iter().try_filter_map(|p| -> TransResult<_> {
+            Ok(match *p {
+                LeanTyParam::TraitRef(_, _, ref bound) if bound.substs.types().all(|ty| ty == bound.self_ty()) => {
+                    let name = krate::name_def_id(self.tcx, bound.def_id);
+                    if self.trait_laws(&name)?.is_some() {
+                        let ty = self.transpile_ty(bound.self_ty())?;
+                        Some(format!("[{} : {}.laws {}]", self.mk_lean_name(format!("{}.laws {}", name, ty)), name, ty))
+                    } else { None }
+                }
+                _ => None,
+            })
+        })?.collect_vec();
+        Ok(Some(format!("-- proof obligation\nlemma {} : {}.laws {} :=\nsorry",
+                        (self.name() + ".laws [instance]", ty_params.iter().map(LeanTyParam::to_string).chain(assumptions)).join(" "),
+                        trait_name, self.transpile_ty(self_ty)?)))
+    }
+}
@@ -0,0 +1,109 @@
+use itertools::Itertools;
+
+use syntax::attr;
+use rustc::traits::Reveal;
+use rustc::ty::{self, Ty, TypeFoldable};
+use rustc::ty::layout::{Layout, TargetDataLayout};
+
+use util::*;
+use trans::item::ItemTranspiler;
+use trans::TransResult;
+
+/// Byte encoding of a type as laid out by rustc, see layout.lean (`layout_model = true`)
+pub struct Codec {
+    /// `T → list u8`
+    pub encode: String,
+    /// `list u8 → option T`, failing on invalid bytes
+    pub decode: String,
+    pub size: u64,
+}
+
+pub fn is_union(ty: Ty) -> bool {
+    match ty.sty {
+        ty::TypeVariants::TyAdt(adt_def, _) => adt_def.adt_kind() == ty::AdtKind::Union,
+        _ => false,
+    }
+}
+
+impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
+    /// Applies `f` to rustc's layout of a concrete type
+    pub fn with_layout<R, F>(&self, ty: Ty<'tcx>, f: F) -> TransResult<R>
+        where F: FnOnce(&Layout, &TargetDataLayout) -> TransResult<R> {
+        let ty = self.mono_ty(ty);
+        if ty.has_param_types() {
+            throw!("unimplemented: layout of generic type |{:?}", ty)
+        }
+        self.tcx.infer_ctxt(None, None, Reveal::All).enter(|infcx| {
+            match ty.layout(&infcx) {
+                Ok(layout) => f(layout, &self.tcx.data_layout),
+                Err(err) => throw!("unimplemented: layout of |{:?}: {}", ty, err),
+            }
+        })
+    }
+
+    /// Encoding of primitive types, arrays of them, `#[repr(C)]` structs and unions
+    pub fn transpile_codec(&self, ty: Ty<'tcx>) -> TransResult<Codec> {
+        let ty = self.mono_ty(ty);
+        let size = self.with_layout(ty, |layout, dl| Ok(layout.size(dl).bytes()))?;
+        let (encode, decode) = match ty.sty {
+            ty::TypeVariants::TyBool =>
+                ("layout.encode_bool".to_string(), "layout.decode_bool".to_string()),
+            ty::TypeVariants::TyChar =>
+                ("layout.encode_char".to_string(), "layout.decode_char".to_string()),
+            ty::TypeVariants::TyUint(_) if self.config.bitvec_ints =>
+                (format!("(λ x, layout.encode_nat {} (bv.to_nat x))", size),
+                 format!("(λ bs, option.map (bv.of_nat {}) (layout.decode_nat {} bs))", self.transpile_bits(ty)?, size)),
+            ty::TypeVariants::TyInt(_) if self.config.bitvec_ints =>
+                (format!("(λ x, layout.encode_int {} (bv.to_int x))", size),
+                 format!("(λ bs, option.map (bv.of_int {}) (layout.decode_int {} bs))", self.transpile_bits(ty)?, size)),
+            ty::TypeVariants::TyUint(_) =>
+                (format!("(layout.encode_nat {})", size), format!("(layout.decode_nat {})", size)),
+            ty::TypeVariants::TyInt(_) =>
+                (format!("(layout.encode_int {})", size), format!("(layout.decode_int {})", size)),
+            ty::TypeVariants::TyArray(elem_ty, n) => {
+                let elem = self.transpile_codec(elem_ty)?;
+                (format!("(layout.encode_array {})", elem.encode),
+                 format!("(layout.decode_array {} {} {})", elem.size, elem.decode, n))
+            }
+            ty::TypeVariants::TyAdt(adt_def, _) if adt_def.adt_kind() == ty::AdtKind::Union => {
+                let name = self.name_def_id(adt_def.did);
+                (format!("{}.bytes", name), format!("(option.map {}.mk ∘ layout.raw {})", name, size))
+            }
+            ty::TypeVariants::TyAdt(adt_def, substs) if adt_def.adt_kind() == ty::AdtKind::Struct &&
+                self.tcx.lookup_repr_hints(adt_def.did).iter().any(|r| *r == attr::ReprExtern || *r == attr::ReprPacked) => {
+                let offsets = self.with_layout(ty, |layout, _| match *layout {
+                    Layout::Univariant { ref variant, .. } => Ok(variant.offsets.iter().map(|o| o.bytes()).collect_vec()),
+                    _ => throw!("unimplemented: byte layout of |{:?}", ty),
+                })?;
+                let fields = adt_def.struct_variant().fields.iter().map(|f| {
+                    self.transpile_codec(self.normalize_ty(f.ty(self.tcx, substs)))
+                }).try()?.collect_vec();
+                let name = self.name_def_id(adt_def.did);
+                let vars = (0..fields.len()).map(|i| format!("x{}", i)).collect_vec();
+                let parts = fields.iter().zip(offsets.iter()).zip(vars.iter()).map(|((field, offset), var)| {
+                    format!("({}, {} {})", offset, field.encode, var)
+                }).join(", ");
+                let encode = format!("(λ x, match x with {} := layout.place {} [{}] end)",
+                                     (format!("{}.mk", name), vars.iter()).join(" "), size, parts);
+                // nested binds, innermost first
+                let decode = fields.iter().zip(offsets.iter()).zip(vars.iter()).rev().fold(
+                    format!("some ({})", (format!("{}.mk", name), vars.iter()).join(" ")),
+                    |cont, ((field, offset), var)| {
+                        format!("option.bind ({} (layout.slice {} {} bs)) (λ {}, {})",
+                                field.decode, offset, field.size, var, cont)
+                    });
+                (encode, format!("(λ bs, option.bind (layout.raw {} bs) (λ bs, {}))", size, decode))
+            }
+            _ => throw!("unimplemented: byte layout of |{:?}", ty),
+        };
+        Ok(Codec { encode: encode, decode: decode, size: size })
+    }
+
+    /// A union is represented by its bytes
+    pub fn transpile_union(&self) -> TransResult {
+        if !self.tcx.item_generics(self.def_id).types.is_empty() {
+            throw!("unimplemented: generic union")
+        }
+        Ok(format!("structure {} := mk ::\n(bytes : list u8)", self.name()))
+    }
+}
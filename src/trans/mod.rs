@@ -1,5 +1,16 @@
 pub mod krate;
 mod item;
+mod consts;
+pub mod cost;
+mod derive;
+mod ghost;
+mod group;
+mod laws;
+mod layout;
+pub mod monad;
+mod mono;
+mod prune;
+mod spec;
 mod fun;
 
 type TransResult<T = String> = Result<T, String>;
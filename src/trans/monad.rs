@@ -0,0 +1,157 @@
+use toml;
+
+/// Lean vocabulary of the semantics monad generated code is written in. Defaults to `sem` (sem.lean), or to
+/// `psem` (psem.lean) with `panic_reasons = true`; a `[monad]` table in the config can instead target e.g. a plain `option` monad, a state-plus-cost monad or a
+/// nondeterminism monad defined in the theory named by `import`. Calls into dependencies are not converted, so
+/// the theory must provide a coercion from the monad they were translated with (usually `sem`, see `opt.of_sem`).
+pub struct Monad {
+    // theory defining the vocabulary, imported by the generated code
+    pub import: Option<String>,
+    // type constructor
+    pub ty: String,
+    // `{bind} x ← m;\n k`
+    pub bind: String,
+    // like `bind`, additionally counting one step (function calls)
+    pub step: String,
+    // `ℕ → m a → m a`, charging additional cost
+    pub incr: String,
+    pub ret: String,
+    pub fail: String,
+    // `option a → m a`
+    pub lift: String,
+    // `(a → b) → m a → m b`
+    pub map: String,
+    // `(s → m (s ⊕ r)) → s → m r`, see loop_combinator.lean
+    pub loop_: String,
+    pub fix: String,
+    // `panic_reason → m a`, `panic_reason → bool → m a → m a`
+    pub panic: String,
+    pub assert: String,
+    // `Π p [decidable p], m a → m a`
+    pub guard: String,
+    // spec predicates `a → m a → Prop`, `m a → Prop`, `(a → Prop) → m a → Prop` (total and partial correctness)
+    pub returns: String,
+    pub terminates: String,
+    pub terminates_with: String,
+    pub on_return: String,
+}
+
+/// Placeholder for operations without a total counterpart, which disqualify a translation from being total
+pub const PARTIAL: &'static str = "«$partial»";
+
+impl Default for Monad {
+    fn default() -> Monad {
+        Monad {
+            import: None,
+            ty: "sem".to_string(),
+            bind: "do".to_string(),
+            step: "dostep".to_string(),
+            incr: "sem.incr".to_string(),
+            ret: "return".to_string(),
+            fail: "mzero".to_string(),
+            lift: "sem.lift_opt".to_string(),
+            map: "sem.map".to_string(),
+            loop_: "loop".to_string(),
+            fix: "fix_opt".to_string(),
+            panic: "sem.panic".to_string(),
+            assert: "sem.assert".to_string(),
+            guard: "sem.guard".to_string(),
+            returns: "sem.returns".to_string(),
+            terminates: "sem.terminates".to_string(),
+            terminates_with: "sem.terminates_with".to_string(),
+            on_return: "sem.on_return".to_string(),
+        }
+    }
+}
+
+lazy_static! {
+    static ref IDENTITY: Monad = Monad::identity();
+}
+
+impl Monad {
+    /// `sem` with panics carrying their reasons, see psem.lean
+    fn psem() -> Monad {
+        Monad {
+            import: Some("psem".to_string()),
+            ty: "psem".to_string(),
+            bind: "pdo".to_string(),
+            step: "pdostep".to_string(),
+            incr: "psem.incr".to_string(),
+            ret: "psem.return".to_string(),
+            fail: "psem.zero".to_string(),
+            lift: "psem.lift".to_string(),
+            map: "psem.map".to_string(),
+            loop_: "psem.loop".to_string(),
+            fix: "fix_opt".to_string(),
+            panic: "psem.panic".to_string(),
+            assert: "psem.assert".to_string(),
+            guard: "psem.guard".to_string(),
+            returns: "psem.returns".to_string(),
+            terminates: "psem.terminates".to_string(),
+            terminates_with: "psem.terminates_with".to_string(),
+            on_return: "psem.on_return".to_string(),
+        }
+    }
+
+    /// Plain values, binding via `let'`
+    fn identity() -> Monad {
+        Monad {
+            import: None,
+            ty: String::new(),
+            bind: "let'".to_string(),
+            step: "let'".to_string(),
+            incr: PARTIAL.to_string(),
+            ret: String::new(),
+            fail: PARTIAL.to_string(),
+            lift: PARTIAL.to_string(),
+            map: PARTIAL.to_string(),
+            loop_: PARTIAL.to_string(),
+            fix: PARTIAL.to_string(),
+            panic: PARTIAL.to_string(),
+            assert: PARTIAL.to_string(),
+            guard: PARTIAL.to_string(),
+            returns: PARTIAL.to_string(),
+            terminates: PARTIAL.to_string(),
+            terminates_with: PARTIAL.to_string(),
+            on_return: PARTIAL.to_string(),
+        }
+    }
+
+    /// Reads the `[monad]` table of the config, falling back to the `sem` (or `psem`) vocabulary for missing entries
+    pub fn new(config: &toml::Value, panic_reasons: bool) -> Monad {
+        let mut monad = if panic_reasons { Monad::psem() } else { Monad::default() };
+        if let Some(table) = config.lookup("monad") {
+            let get = |key: &str, default: &mut String| {
+                if let Some(val) = table.lookup(key) {
+                    *default = val.as_str().unwrap_or_else(|| panic!("'monad.{}' must be a string", key)).to_string();
+                }
+            };
+            get("type", &mut monad.ty);
+            get("bind", &mut monad.bind);
+            get("step", &mut monad.step);
+            get("incr", &mut monad.incr);
+            get("return", &mut monad.ret);
+            get("fail", &mut monad.fail);
+            get("lift", &mut monad.lift);
+            get("map", &mut monad.map);
+            get("loop", &mut monad.loop_);
+            get("fix", &mut monad.fix);
+            get("panic", &mut monad.panic);
+            get("assert", &mut monad.assert);
+            get("guard", &mut monad.guard);
+            get("returns", &mut monad.returns);
+            get("terminates", &mut monad.terminates);
+            get("terminates_with", &mut monad.terminates_with);
+            get("on_return", &mut monad.on_return);
+            if let Some(import) = table.lookup("import") {
+                monad.import = Some(import.as_str().expect("'monad.import' must be a string").to_string());
+            }
+        }
+        monad
+    }
+}
+
+/// Vocabulary of the total translation of ghost functions, see `FnTranspiler::new_total`
+pub fn identity() -> &'static Monad {
+    &IDENTITY
+}
@@ -0,0 +1,108 @@
+use itertools::Itertools;
+
+use rustc::hir::def_id::DefId;
+use rustc::traits::Reveal;
+use rustc::ty::{self, Ty, TypeFoldable};
+use rustc::ty::subst::{Subst, Substs};
+
+use trans::fun::{FnTranspiler, has_mir};
+use trans::item::{ItemTranspiler, TraitImplLookup};
+use trans::krate::{self, CrateTranspiler};
+use trans::TransResult;
+
+// bound on the instances of a single item, e.g. against polymorphic recursion
+const MAX_INSTANCES: usize = 64;
+
+impl<'a, 'tcx> CrateTranspiler<'a, 'tcx> {
+    /// Generic functions and closures are only emitted as their requested instances (`monomorphize = true`)
+    pub fn is_mono_item(&self, def_id: DefId) -> bool {
+        let generics = self.tcx.item_generics(def_id);
+        self.config.monomorphize && has_mir(self.tcx, def_id) && (generics.parent_types > 0 || !generics.types.is_empty())
+    }
+
+    fn request_instance(&self, def_id: DefId, name: String, substs: &'tcx Substs<'tcx>) {
+        let mut instances = self.mono_instances.borrow_mut();
+        let instances = instances.entry(def_id).or_insert_with(Vec::new);
+        if instances.len() <= MAX_INSTANCES && !instances.iter().any(|&(ref other, _)| *other == name) {
+            instances.push((name, substs));
+            // (re)translate on the next occasion
+            self.mono_stale.borrow_mut().insert(def_id);
+        }
+    }
+
+    /// Translates an item, or all requested instances of a generic one
+    pub fn transpile_instances(&self, def_id: DefId) -> TransResult<Option<String>> {
+        if !self.is_mono_item(def_id) {
+            return ItemTranspiler { sup: self, def_id: def_id, substs: None }.transpile_def_id()
+        }
+        let instances = self.mono_instances.borrow().get(&def_id).cloned().unwrap_or_else(Vec::new);
+        if instances.len() > MAX_INSTANCES {
+            throw!("unimplemented: more than {} instances |(polymorphic recursion?)", MAX_INSTANCES)
+        }
+        let defs = instances.into_iter().map(|(_, substs)| {
+            ItemTranspiler { sup: self, def_id: def_id, substs: Some(substs) }.transpile_def_id()
+        }).try()?.filter_map(|def| def).collect_vec();
+        Ok(if defs.is_empty() { None } else { Some(defs.join("\n\n")) })
+    }
+}
+
+impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
+    /// `f` instantiated with `[u32, bool]` ~> `f.«u32, bool»`
+    pub fn instance_name(&self, def_id: DefId, substs: &'tcx Substs<'tcx>) -> String {
+        let tys = substs.types().map(|ty| self.tcx.erase_regions(&self.normalize_ty(ty)).to_string()).collect_vec();
+        let name = krate::name_def_id(self.tcx, def_id);
+        if tys.is_empty() { name } else {
+            format!("{}.{}", name, self.mk_lean_name(tys.join(", ")))
+        }
+    }
+
+    /// Name of a concrete instance, which is translated along with its item
+    pub fn mono_name(&self, def_id: DefId, substs: &'tcx Substs<'tcx>) -> String {
+        self.add_dep(def_id);
+        let name = self.instance_name(def_id, substs);
+        if self.is_mono_item(def_id) {
+            self.request_instance(def_id, name.clone(), substs);
+        }
+        name
+    }
+
+    /// Substitutes the concrete types of the instance being translated
+    pub fn mono_substs(&self, substs: &'tcx Substs<'tcx>) -> &'tcx Substs<'tcx> {
+        match self.substs {
+            Some(outer) => substs.subst(self.tcx, outer),
+            None => substs,
+        }
+    }
+
+    pub fn mono_ty(&self, ty: Ty<'tcx>) -> Ty<'tcx> {
+        match self.substs {
+            Some(outer) if ty.has_param_types() || ty.has_projection_types() =>
+                self.normalize_ty(ty.subst(self.tcx, outer)),
+            _ => ty,
+        }
+    }
+
+    /// Concrete callee of a reference to `def_id` from the current item, with trait methods resolved to their impl.
+    /// `None` without monomorphization and for other crates' items, which keep their dictionary-passing signatures.
+    pub fn resolve_instance(&self, def_id: DefId, substs: &'tcx Substs<'tcx>) -> TransResult<Option<(DefId, &'tcx Substs<'tcx>)>> {
+        if !self.config.monomorphize {
+            return Ok(None)
+        }
+        let substs = self.mono_substs(substs);
+        let instance = match self.tcx.trait_of_item(def_id) {
+            Some(trait_def_id) => {
+                let trait_ref = ty::TraitRef::from_method(self.tcx, trait_def_id, substs);
+                self.tcx.infer_ctxt(None, Some(ty::ParameterEnvironment::for_item(self.tcx, self.node_id())), Reveal::All).enter(|infcx| -> TransResult<_> {
+                    Ok(match self.infer_trait_impl(trait_ref, &infcx)? {
+                        TraitImplLookup::Static { impl_def_id, substs: impl_substs, .. } =>
+                            Some(FnTranspiler::get_impl_method(self.tcx, substs, impl_def_id, impl_substs, self.tcx.item_name(def_id))),
+                        // closures and function pointers
+                        TraitImplLookup::Dynamic { .. } => None,
+                    })
+                })?
+            }
+            None => Some((def_id, substs)),
+        };
+        Ok(instance.and_then(|(def_id, substs)| if def_id.is_local() { Some((def_id, substs)) } else { None }))
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+
+use itertools::Itertools;
+
+use syntax::ast;
+use rustc::hir::def_id::DefId;
+use rustc::mir::*;
+use rustc::mir::visit::Visitor;
+use rustc::traits::Reveal;
+use rustc::ty;
+use rustc::ty::subst::Substs;
+
+use trans::fun::{FnTranspiler, has_mir};
+use trans::item::{ItemTranspiler, TraitImplLookup};
+use trans::krate::{self, CrateTranspiler};
+
+/// Collects references to items, i.e. calls and items used as values
+struct ItemRefCollector<'tcx> {
+    refs: Vec<(DefId, &'tcx Substs<'tcx>)>,
+}
+
+impl<'tcx> Visitor<'tcx> for ItemRefCollector<'tcx> {
+    fn visit_constant(&mut self, constant: &Constant<'tcx>, location: Location) {
+        if let Literal::Item { def_id, substs } = constant.literal {
+            self.refs.push((def_id, substs));
+        }
+        self.super_constant(constant, location);
+    }
+}
+
+impl<'a, 'tcx> CrateTranspiler<'a, 'tcx> {
+    /// Finds all trait methods of the crate's traits that are called through a class instance, i.e. that are not
+    /// resolved to an impl by `get_call_target`, from the `roots` or from functions reachable from them
+    /// (`prune_trait_methods = true`)
+    pub fn collect_dyn_methods(&mut self, def_ids: &[DefId], roots: &[DefId]) {
+        if !self.config.prune_trait_methods {
+            return
+        }
+        let mut dyn_methods = HashSet::new();
+        // other crates may call methods of impls of their traits
+        let mut todo = roots.iter().cloned().chain(def_ids.iter().cloned().filter(|&def_id| {
+            self.tcx.impl_of_method(def_id).and_then(|impl_did| self.tcx.trait_id_of_impl(impl_did))
+                .map_or(false, |trait_did| !trait_did.is_local())
+        })).collect_vec();
+        let mut visited = HashSet::new();
+        while let Some(def_id) = todo.pop() {
+            if !def_id.is_local() || !visited.insert(def_id) || !has_mir(self.tcx, def_id) {
+                continue
+            }
+            let mut collector = ItemRefCollector { refs: vec![] };
+            collector.visit_mir(&self.tcx.item_mir(def_id));
+            let caller = ItemTranspiler { sup: self, def_id: def_id, substs: None };
+            for (callee, substs) in collector.refs {
+                let trait_did = match self.tcx.trait_of_item(callee) {
+                    Some(trait_did) => trait_did,
+                    None => {
+                        todo.push(callee);
+                        continue
+                    }
+                };
+                match caller.static_call_target(callee, substs) {
+                    Some(target) => todo.push(target),
+                    None => {
+                        // any impl may be called, as well as the default method
+                        let name = self.tcx.item_name(callee);
+                        todo.push(callee);
+                        self.tcx.lookup_trait_def(trait_did).for_each_impl(self.tcx, |impl_did| {
+                            todo.extend(self.tcx.associated_items(impl_did).filter(|item| item.name == name).map(|item| item.def_id));
+                        });
+                        if callee.is_local() {
+                            dyn_methods.insert(callee);
+                        }
+                    }
+                }
+            }
+        }
+        // resolving instances records dependencies of items that have not been translated yet
+        *self.deps.borrow_mut() = Default::default();
+        self.dyn_methods = dyn_methods;
+    }
+}
+
+impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
+    /// The impl method a call of the trait method from the current item is resolved to, if any
+    pub fn static_call_target(&self, method: DefId, substs: &'tcx Substs<'tcx>) -> Option<DefId> {
+        let trait_ref = ty::TraitRef::from_method(self.tcx, self.tcx.trait_of_item(method).unwrap(), substs);
+        self.tcx.infer_ctxt(None, Some(ty::ParameterEnvironment::for_item(self.tcx, self.node_id())), Reveal::All).enter(|infcx| {
+            match self.infer_trait_impl(trait_ref, &infcx) {
+                Ok(TraitImplLookup::Static { impl_def_id, substs: impl_substs, .. }) =>
+                    Some(FnTranspiler::get_impl_method(self.tcx, substs, impl_def_id, impl_substs, self.tcx.item_name(method)).0),
+                _ => None,
+            }
+        })
+    }
+
+    /// Whether the trait method is a field of the trait's class and its instances: either listed in
+    /// `traits."<trait>".only`, or, with pruning, called dynamically or listed in `traits."<trait>".keep`.
+    /// Instances of other crates' traits can only rely on `only`.
+    pub fn is_class_method(&self, trait_did: DefId, name: ast::Name) -> bool {
+        let trait_name = krate::name_def_id(self.tcx, trait_did);
+        let lookup = |key: &str| self.config.config.lookup(&format!("traits.\"{}\".{}", trait_name, key))
+            .map_or(false, |methods| ::toml_value_as_str_array(methods).contains(&&*name.as_str()));
+        if self.config.config.lookup(&format!("traits.\"{}\".only", trait_name)).is_some() {
+            return lookup("only")
+        }
+        if !self.config.prune_trait_methods || !trait_did.is_local() {
+            return true
+        }
+        lookup("keep") || self.tcx.associated_items(trait_did).any(|item| {
+            item.name == name && self.dyn_methods.contains(&item.def_id)
+        })
+    }
+}
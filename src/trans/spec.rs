@@ -0,0 +1,425 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use itertools::Itertools;
+
+use rustc::hir::def::CtorKind;
+use rustc::hir::def_id::DefId;
+use rustc::ty::{self, Ty};
+use rustc::ty::subst::Substs;
+use syntax::parse::lexer::comments::strip_doc_comment_decoration;
+
+use util::*;
+use trans::fun::{get_tuple_elem, unwrap_refs};
+use trans::item::ItemTranspiler;
+use trans::krate;
+use trans::TransResult;
+
+// A small Rust-like expression language for specifications, given as `#[<key> = "..."]` attributes
+// (`#![feature(custom_attribute)]`) or as `/// @<key> ...` lines in the documentation of an item
+
+enum Token {
+    Ident(String),
+    Int(u64),
+    Op(&'static str),
+}
+
+const OPS: &'static [&'static str] = &[
+    "==>", "==", "!=", "<=", ">=", "&&", "||", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")", ".", ",",
+];
+
+fn tokenize(src: &str) -> TransResult<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars: Peekable<Chars> = src.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') { break }
+                ident.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Ident(ident));
+        } else if c.is_digit(10) {
+            let mut n = 0u64;
+            while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+                n = n * 10 + d as u64;
+                chars.next();
+            }
+            tokens.push(Token::Int(n));
+        } else {
+            let rest = chars.clone().collect::<String>();
+            match OPS.iter().find(|op| rest.starts_with(**op)) {
+                Some(&op) => {
+                    for _ in 0..op.chars().count() { chars.next(); }
+                    tokens.push(Token::Op(op));
+                }
+                None => throw!("invalid spec |unexpected '{}' in `{}`", c, src),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+enum Expr {
+    Var(String),
+    Int(u64),
+    Bool(bool),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    // `e.f`, `e.0`
+    Field(Box<Expr>, String),
+    // `e.m(args)`
+    Method(Box<Expr>, String, Vec<Expr>),
+    // `f(args)` of a ghost function, see `trans::ghost`
+    Call(String, Vec<Expr>),
+}
+
+/// Recursive descent over the usual Rust precedences, with `==>` binding weakest
+struct Parser<'s> {
+    src: &'s str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(&Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, op: &'static str) -> TransResult<()> {
+        if self.peek_op() != Some(op) {
+            throw!("invalid spec |expected '{}' in `{}`", op, self.src)
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn binary(&mut self, ops: &[&'static str], next: fn(&mut Parser<'s>) -> TransResult<Expr>) -> TransResult<Expr> {
+        let mut lhs = next(self)?;
+        while let Some(op) = self.peek_op().and_then(|op| ops.iter().find(|&&o| o == op).cloned()) {
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(next(self)?));
+        }
+        Ok(lhs)
+    }
+
+    fn implication(&mut self) -> TransResult<Expr> {
+        let lhs = self.disjunction()?;
+        if self.peek_op() == Some("==>") {
+            self.pos += 1;
+            return Ok(Expr::Binary("==>", Box::new(lhs), Box::new(self.implication()?)))
+        }
+        Ok(lhs)
+    }
+
+    fn disjunction(&mut self) -> TransResult<Expr> { self.binary(&["||"], Parser::conjunction) }
+    fn conjunction(&mut self) -> TransResult<Expr> { self.binary(&["&&"], Parser::comparison) }
+
+    fn comparison(&mut self) -> TransResult<Expr> {
+        let lhs = self.sum()?;
+        match self.peek_op() {
+            Some(op @ "==") | Some(op @ "!=") | Some(op @ "<") | Some(op @ "<=") | Some(op @ ">") | Some(op @ ">=") => {
+                self.pos += 1;
+                Ok(Expr::Binary(op, Box::new(lhs), Box::new(self.sum()?)))
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    fn sum(&mut self) -> TransResult<Expr> { self.binary(&["+", "-"], Parser::product) }
+    fn product(&mut self) -> TransResult<Expr> { self.binary(&["*", "/", "%"], Parser::unary) }
+
+    fn unary(&mut self) -> TransResult<Expr> {
+        match self.peek_op() {
+            Some(op @ "!") | Some(op @ "-") => {
+                self.pos += 1;
+                Ok(Expr::Unary(op, Box::new(self.unary()?)))
+            }
+            _ => self.postfix(),
+        }
+    }
+
+    fn postfix(&mut self) -> TransResult<Expr> {
+        let mut e = self.atom()?;
+        while self.peek_op() == Some(".") {
+            self.pos += 1;
+            let name = match self.tokens.get(self.pos) {
+                Some(&Token::Ident(ref name)) => name.clone(),
+                Some(&Token::Int(n)) => n.to_string(),
+                _ => throw!("invalid spec |expected field or method in `{}`", self.src),
+            };
+            self.pos += 1;
+            e = if self.peek_op() == Some("(") {
+                Expr::Method(Box::new(e), name, self.args()?)
+            } else {
+                Expr::Field(Box::new(e), name)
+            };
+        }
+        Ok(e)
+    }
+
+    /// `(a, b, ...)`
+    fn args(&mut self) -> TransResult<Vec<Expr>> {
+        self.expect("(")?;
+        let mut args = vec![];
+        while self.peek_op() != Some(")") {
+            args.push(self.implication()?);
+            if self.peek_op() != Some(")") {
+                self.expect(",")?;
+            }
+        }
+        self.expect(")")?;
+        Ok(args)
+    }
+
+    fn atom(&mut self) -> TransResult<Expr> {
+        if self.peek_op() == Some("(") {
+            self.pos += 1;
+            let e = self.implication()?;
+            self.expect(")")?;
+            return Ok(e)
+        }
+        let e = match self.tokens.get(self.pos) {
+            Some(&Token::Int(n)) => Expr::Int(n),
+            Some(&Token::Ident(ref name)) if name == "true" => Expr::Bool(true),
+            Some(&Token::Ident(ref name)) if name == "false" => Expr::Bool(false),
+            Some(&Token::Ident(ref name)) => Expr::Var(name.clone()),
+            _ => throw!("invalid spec |unexpected end or operator in `{}`", self.src),
+        };
+        self.pos += 1;
+        match e {
+            Expr::Var(name) => if self.peek_op() == Some("(") {
+                Ok(Expr::Call(name, self.args()?))
+            } else { Ok(Expr::Var(name)) },
+            e => Ok(e),
+        }
+    }
+}
+
+fn parse(src: &str) -> TransResult<Expr> {
+    let mut parser = Parser { src: src, tokens: tokenize(src)?, pos: 0 };
+    let e = parser.implication()?;
+    if parser.pos != parser.tokens.len() {
+        throw!("invalid spec |trailing tokens in `{}`", src)
+    }
+    Ok(e)
+}
+
+/// Definitions generated from the specs of `name`, or a comment explaining why they are missing: specs only add to
+/// the translation of an item and should not make it fail
+pub fn or_comment<T: IntoIterator<Item=String>>(name: &str, res: TransResult<T>) -> Vec<String> {
+    match res {
+        Ok(defs) => defs.into_iter().collect(),
+        Err(err) => vec![format!("/- {}: {} -/", name, err.replace("/-", "/ -"))],
+    }
+}
+
+/// Variables visible in a spec by their Rust names, with their Lean names and types
+pub type Scope<'tcx> = Vec<(String, String, Ty<'tcx>)>;
+
+/// A translated spec expression: a proposition or a term of a (possibly unknown) Rust type
+enum Value<'tcx> {
+    Prop(String),
+    Term(String, Option<Ty<'tcx>>),
+}
+
+impl<'tcx> Value<'tcx> {
+    /// bool-valued terms are compared to `tt`
+    fn into_prop(self) -> String {
+        match self {
+            Value::Prop(p) => p,
+            Value::Term(t, _) => format!("({} = tt)", t),
+        }
+    }
+
+    fn into_term(self, src: &str) -> TransResult {
+        match self {
+            Value::Term(t, _) => Ok(t),
+            Value::Prop(_) => throw!("unimplemented: spec |proposition used as value in `{}`", src),
+        }
+    }
+}
+
+
+impl<'a, 'tcx> ItemTranspiler<'a, 'tcx> {
+    /// Sources of the `key` specs of an item
+    pub fn spec_attrs(&self, def_id: DefId, key: &str) -> Vec<String> {
+        let doc_prefix = format!("@{} ", key);
+        let mut specs = vec![];
+        for attr in self.tcx.get_attrs(def_id).iter() {
+            let val = match attr.value_str() {
+                Some(val) => val,
+                None => continue,
+            };
+            if attr.check_name(key) {
+                specs.push(val.to_string());
+            } else if attr.is_sugared_doc {
+                specs.extend(strip_doc_comment_decoration(&val).lines().map(str::trim).filter(|line| {
+                    line.starts_with(&doc_prefix)
+                }).map(|line| line[doc_prefix.len()..].to_string()));
+            }
+        }
+        specs
+    }
+
+    /// `definition S.inv (self : S) : Prop` from the `invariant` specs of a struct, e.g.
+    /// `#[invariant = "self.length <= self.data.len() * 32"]`
+    pub fn transpile_struct_invariant(&self) -> TransResult<Option<String>> {
+        let invariants = self.spec_attrs(self.def_id, "invariant");
+        if invariants.is_empty() {
+            return Ok(None)
+        }
+        let scope = vec![("self".to_string(), "self".to_string(), self.tcx.item_type(self.def_id))];
+        let generics = self.tcx.item_generics(self.def_id);
+        Ok(Some(format!("definition {} (self : {}) : Prop :=\n{}",
+                        (self.name() + ".inv", generics.types.iter().map(|p| format!("{{{} : Type₁}}", p.name))).join(" "),
+                        self.mk_applied_ty(&self.name(), generics),
+                        invariants.iter().map(|src| self.spec_prop(src, &scope)).try()?.join(" ∧ "))))
+    }
+
+    /// `S.inv` if `ty` is (a reference to) a struct with invariants that could be translated
+    pub fn invariant_of(&self, ty: Ty<'tcx>) -> Option<String> {
+        match unwrap_refs(self.unwrap_mut_ref(ty)).sty {
+            ty::TypeVariants::TyAdt(adt_def, _) if !self.spec_attrs(adt_def.did, "invariant").is_empty() => {
+                let item = ItemTranspiler { sup: self.sup, def_id: adt_def.did, substs: None };
+                match item.transpile_struct_invariant() {
+                    Ok(Some(_)) => Some(self.name_def_id(adt_def.did) + ".inv"),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Elements of a `Vec` (see `collections/pre.lean`) or bytes of a `String`
+    fn vec_buf(&self, adt_def: ty::AdtDef<'tcx>, substs: &'tcx Substs<'tcx>, t: &str) -> Option<String> {
+        match &krate::name_def_id(self.tcx, adt_def.did)[..] {
+            "collections.vec.Vec" => Some(format!("({}.buf {})", self.name_def_id(adt_def.did), t)),
+            "collections.string.String" => {
+                let vec = format!("({}.vec {})", self.name_def_id(adt_def.did), t);
+                match adt_def.struct_variant().fields.iter().find(|f| &*f.name.as_str() == "vec").map(|f| &f.ty(self.tcx, substs).sty) {
+                    Some(&ty::TypeVariants::TyAdt(vec_def, vec_substs)) => self.vec_buf(vec_def, vec_substs, &vec),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn spec_prop(&self, src: &str, scope: &Scope<'tcx>) -> TransResult {
+        if self.config.bitvec_ints {
+            throw!("unimplemented: spec |with bit vector integers")
+        }
+        Ok(self.transpile_spec_expr(&parse(src)?, scope, src)?.into_prop())
+    }
+
+    pub fn spec_term(&self, src: &str, scope: &Scope<'tcx>) -> TransResult {
+        if self.config.bitvec_ints {
+            throw!("unimplemented: spec |with bit vector integers")
+        }
+        self.transpile_spec_expr(&parse(src)?, scope, src)?.into_term(src)
+    }
+
+    fn transpile_spec_expr(&self, e: &Expr, scope: &Scope<'tcx>, src: &str) -> TransResult<Value<'tcx>> {
+        Ok(match *e {
+            Expr::Var(ref name) => match scope.iter().find(|&&(ref var, _, _)| var == name) {
+                Some(&(_, ref lean, ty)) => Value::Term(lean.clone(), Some(ty)),
+                None => throw!("invalid spec |unknown variable `{}` in `{}`", name, src),
+            },
+            Expr::Int(n) => Value::Term(n.to_string(), None),
+            Expr::Bool(b) => Value::Prop(b.to_string()),
+            Expr::Unary("!", ref e) => Value::Prop(format!("¬{}", self.transpile_spec_expr(e, scope, src)?.into_prop())),
+            Expr::Unary(_, ref e) => Value::Term(format!("-{}", self.transpile_spec_expr(e, scope, src)?.into_term(src)?), None),
+            Expr::Binary(op, ref a, ref b) => {
+                let (a, b) = (self.transpile_spec_expr(a, scope, src)?, self.transpile_spec_expr(b, scope, src)?);
+                let is_prop = |v: &Value| match *v { Value::Prop(_) => true, _ => false };
+                let connective = match op {
+                    "==>" => Some("→"),
+                    "||" => Some("∨"),
+                    "&&" => Some("∧"),
+                    "==" if is_prop(&a) || is_prop(&b) => Some("↔"),
+                    _ => None,
+                };
+                match connective {
+                    Some(connective) => Value::Prop(format!("({} {} {})", a.into_prop(), connective, b.into_prop())),
+                    None if op == "!=" && (is_prop(&a) || is_prop(&b)) =>
+                        Value::Prop(format!("¬({} ↔ {})", a.into_prop(), b.into_prop())),
+                    None => {
+                        let (a, b) = (a.into_term(src)?, b.into_term(src)?);
+                        match op {
+                            "==" => Value::Prop(format!("({} = {})", a, b)),
+                            "!=" => Value::Prop(format!("({} ≠ {})", a, b)),
+                            "<" | ">" => Value::Prop(format!("({} {} {})", a, op, b)),
+                            "<=" => Value::Prop(format!("({} ≤ {})", a, b)),
+                            ">=" => Value::Prop(format!("({} ≥ {})", a, b)),
+                            _ => Value::Term(format!("({} {} {})", a, op, b), None),
+                        }
+                    }
+                }
+            }
+            Expr::Field(ref base, ref field) => {
+                let (sbase, ty) = match self.transpile_spec_expr(base, scope, src)? {
+                    Value::Term(t, Some(ty)) => (t, unwrap_refs(self.unwrap_mut_ref(ty))),
+                    _ => throw!("unimplemented: spec |field access on untyped value in `{}`", src),
+                };
+                match ty.sty {
+                    ty::TypeVariants::TyTuple(tys) => match field.parse::<usize>().ok().and_then(|i| tys.get(i).map(|&ty| (i, ty))) {
+                        Some((i, field_ty)) => Value::Term(get_tuple_elem(sbase, i, tys.len()), Some(field_ty)),
+                        None => throw!("invalid spec |no field `{}` in `{}`", field, src),
+                    },
+                    // accessors of type group members unwrap auxiliary types of nested fields as well, see
+                    // `transpile_group_struct`
+                    ty::TypeVariants::TyAdt(adt_def, substs) if adt_def.adt_kind() == ty::AdtKind::Struct &&
+                        adt_def.struct_variant().ctor_kind == CtorKind::Fictive => {
+                        match adt_def.struct_variant().fields.iter().find(|f| *f.name.as_str() == **field) {
+                            Some(f) => Value::Term(format!("({}.{} {})", self.name_def_id(adt_def.did),
+                                                           self.mk_lean_name(&*f.name.as_str()), sbase),
+                                                   Some(f.ty(self.tcx, substs))),
+                            None => throw!("invalid spec |no field `{}` in `{}`", field, src),
+                        }
+                    }
+                    _ => throw!("unimplemented: spec |field `{}` of {:?}", field, ty),
+                }
+            }
+            Expr::Method(ref recv, ref method, ref args) if method == "len" && args.is_empty() => {
+                match self.transpile_spec_expr(recv, scope, src)? {
+                    Value::Term(t, Some(ty)) => match unwrap_refs(self.unwrap_mut_ref(ty)).sty {
+                        ty::TypeVariants::TySlice(_) | ty::TypeVariants::TyArray(..) =>
+                            Value::Term(format!("(list.length {})", t), None),
+                        ty::TypeVariants::TyAdt(adt_def, substs) => match self.vec_buf(adt_def, substs, &t) {
+                            Some(buf) => Value::Term(format!("(list.length {})", buf), None),
+                            None => throw!("unimplemented: spec |`len` of {:?}", ty),
+                        },
+                        ref ty => throw!("unimplemented: spec |`len` of {:?}", ty),
+                    },
+                    _ => throw!("unimplemented: spec |`len` of untyped value in `{}`", src),
+                }
+            }
+            Expr::Method(_, ref method, _) => throw!("unimplemented: spec |method `{}` in `{}`", method, src),
+            Expr::Call(ref name, ref args) => {
+                let def_id = match self.spec_fns.get(name).map(|def_ids| &def_ids[..]) {
+                    Some(&[def_id]) => def_id,
+                    Some(def_ids) => throw!("invalid spec |ambiguous function `{}` in `{}`, defined as {}", name, src,
+                                            def_ids.iter().map(|&def_id| self.tcx.item_path_str(def_id)).join(", ")),
+                    None => throw!("invalid spec |unknown function `{}` in `{}`", name, src),
+                };
+                let args = args.iter().map(|arg| self.transpile_spec_expr(arg, scope, src)?.into_term(src)).try()?;
+                let app = format!("({})", (self.name_def_id(def_id), args).join(" "));
+                let ret_ty = self.tcx.item_type(def_id).fn_sig().skip_binder().output;
+                if self.pure_fn(def_id).is_some() {
+                    Value::Term(app, Some(ret_ty))
+                } else if ret_ty.is_bool() {
+                    Value::Prop(format!("{} tt {}", self.monad().returns, app))
+                } else {
+                    throw!("unimplemented: spec |call of partial function `{}` in `{}`", name, src)
+                }
+            }
+        })
+    }
+}
@@ -1,17 +1,157 @@
 import data.list
 import core.pre
+import heap
 
 open list
+open prod.ops
 
 namespace alloc
 
 namespace boxed
-  structure Box (T : Type₁) :=
-  (val : T)
+  -- transparent, so that recursive types can be defined through it
+  definition Box [reducible] (T : Type₁) := T
 
-  definition «Box<T>».new {T : Type₁} (val : T) : sem (Box T) := return (Box.mk val)
+  definition «Box<T>».new {T : Type₁} (val : T) : sem (Box T) := return val
 end boxed
 
+-- `Rc<T>`/`Arc<T>` and their `Weak<T>` are pointers to a cell in the heap of heap.lean (`heap_model = true`)
+-- holding the reference counts and the shared value. Their methods are translated as calls of `handle`, see
+-- `rc_model` in intrinsics.rs.
+structure rc_box (T : Type₁) := mk {} ::
+(strong : ℕ)
+(weak : ℕ) -- including the one held by all strong handles
+(value : T)
+
+namespace rc_box
+  definition alloc {T : Type₁} (value : T) (h : heap) : sem (ptr (rc_box T) × heap) :=
+  do r ← sem.lift_opt (heap.alloc h 3 1);
+  do p ← sem.lift_opt (ptr.cast r.1);
+  sem.map (λ h', (p, h')) (sem.lift_opt (heap.write r.2 p (rc_box.mk 1 1 value)))
+
+  definition get {T : Type₁} (p : ptr (rc_box T)) (h : heap) : sem (rc_box T) := sem.lift_opt (heap.read h p)
+
+  definition modify {T : Type₁} (f : rc_box T → rc_box T) (p : ptr (rc_box T)) (h : heap) : sem heap :=
+  do c ← get p h;
+  sem.lift_opt (heap.write h p (f c))
+
+  definition incr_strong {T : Type₁} := @modify T (λ c, ⦃ rc_box, strong := strong c + 1, c ⦄)
+  definition incr_weak {T : Type₁} := @modify T (λ c, ⦃ rc_box, weak := weak c + 1, c ⦄)
+
+  definition release_weak {T : Type₁} (p : ptr (rc_box T)) (h : heap) : sem heap :=
+  do c ← get p h;
+  if weak c = 1 then
+    do q ← sem.lift_opt (ptr.cast p);
+    sem.lift_opt (heap.dealloc h q 3 1)
+  else modify (λ c, ⦃ rc_box, weak := weak c - 1, c ⦄) p h
+
+  -- the last strong handle also gives back the implicit weak reference
+  definition release_strong {T : Type₁} (p : ptr (rc_box T)) (h : heap) : sem heap :=
+  do c ← get p h;
+  if strong c = 1 then
+    do h ← modify (λ c, ⦃ rc_box, strong := 0, c ⦄) p h;
+    release_weak p h
+  else modify (λ c, ⦃ rc_box, strong := strong c - 1, c ⦄) p h
+end rc_box
+
+-- A `&mut T` from `get_mut`/`make_mut` is a lens into the (then unique) handle, so the value is checked out into
+-- the handle and only written back to the cell when another handle could observe it (`handle.flush`).
+structure handle (T : Type₁) := mk {} ::
+(cell : ptr (rc_box T))
+(checked_out : option T)
+
+namespace handle
+  definition new {T : Type₁} (value : T) (h : heap) : sem (handle T × heap) :=
+  sem.map (λ r, (handle.mk r.1 none, r.2)) (rc_box.alloc value h)
+
+  definition value {T : Type₁} (self : handle T) (h : heap) : sem T :=
+  match checked_out self with
+  | some v := return v
+  | none   := sem.map rc_box.value (rc_box.get (cell self) h)
+  end
+
+  definition flush {T : Type₁} (self : handle T) (h : heap) : sem heap :=
+  match checked_out self with
+  | some v := rc_box.modify (λ c, ⦃ rc_box, value := v, c ⦄) (cell self) h
+  | none   := return h
+  end
+
+  definition checkout {T : Type₁} (self : handle T) (h : heap) : sem (handle T) :=
+  sem.map (λ v, handle.mk (cell self) (some v)) (value self h)
+
+  definition value_lens [constructor] {T : Type₁} : lens (handle T) T :=
+  lens.mk (λ o, sem.lift_opt (checked_out o)) (λ o i, return (handle.mk (cell o) (some i)))
+
+  -- another handle to the same cell
+  definition share {T : Type₁} (self : handle T) (h : heap) : sem (handle T × heap) :=
+  sem.map (λ h', (handle.mk (cell self) none, h')) (flush self h)
+
+  definition strong_count {T : Type₁} (self : handle T) (h : heap) : sem (usize × heap) :=
+  sem.map (λ c, (rc_box.strong c, h)) (rc_box.get (cell self) h)
+  definition weak_count {T : Type₁} (self : handle T) (h : heap) : sem (usize × heap) :=
+  sem.map (λ c, (rc_box.weak c - 1, h)) (rc_box.get (cell self) h)
+
+  definition deref {T : Type₁} (self : handle T) (h : heap) : sem (T × heap) :=
+  sem.map (λ v, (v, h)) (value self h)
+
+  definition clone {T : Type₁} (self : handle T) (h : heap) : sem (handle T × heap) :=
+  do r ← share self h;
+  sem.map (λ h', (r.1, h')) (rc_box.incr_strong (cell self) r.2)
+  definition downgrade {T : Type₁} (self : handle T) (h : heap) : sem (handle T × heap) :=
+  do r ← share self h;
+  sem.map (λ h', (r.1, h')) (rc_box.incr_weak (cell self) r.2)
+  definition clone_weak {T : Type₁} (self : handle T) (h : heap) : sem (handle T × heap) :=
+  sem.map (λ h', (self, h')) (rc_box.incr_weak (cell self) h)
+
+  definition upgrade {T : Type₁} (self : handle T) (h : heap) : sem (option.Option (handle T) × heap) :=
+  do c ← rc_box.get (cell self) h;
+  if rc_box.strong c = 0 then return (option.Option.None, h)
+  else sem.map (λ h', (option.Option.Some (handle.mk (cell self) none), h')) (rc_box.incr_strong (cell self) h)
+
+  -- `Rc::get_mut`: no other strong or weak handles
+  definition get_mut {T : Type₁} (self : handle T) (h : heap) :
+    sem (option.Option (lens (handle T) T) × handle T × heap) :=
+  do c ← rc_box.get (cell self) h;
+  if rc_box.strong c = 1 ∧ rc_box.weak c = 1 then
+    sem.map (λ self', (option.Option.Some value_lens, self', h)) (checkout self h)
+  else return (option.Option.None, self, h)
+
+  /- clones the value into a fresh cell unless `self` is unique; with only weak handles left, the value is
+     moved instead and the weak handles are disassociated -/
+  definition make_mut {T : Type₁} [core.clone.Clone T] (self : handle T) (h : heap) :
+    sem (lens (handle T) T × handle T × heap) :=
+  do c ← rc_box.get (cell self) h;
+  if rc_box.strong c = 1 ∧ rc_box.weak c = 1 then
+    sem.map (λ self', (value_lens, self', h)) (checkout self h)
+  else
+    do v ← value self h;
+    do v ← if rc_box.strong c = 1 then return v else core.clone.Clone.clone v;
+    do h ← rc_box.release_strong (cell self) h;
+    sem.map (λ r, (value_lens, handle.mk r.1 (some v), r.2)) (rc_box.alloc v h)
+
+  -- emitted for `Drop` terminators
+  definition release {T : Type₁} (self : handle T) (h : heap) : sem heap :=
+  do h ← flush self h;
+  rc_box.release_strong (cell self) h
+  definition release_weak {T : Type₁} (self : handle T) : heap → sem heap := rc_box.release_weak (cell self)
+end handle
+
+namespace rc
+  definition Rc [reducible] (T : Type₁) := handle T
+  definition Weak [reducible] (T : Type₁) := handle T
+
+  definition Rc.release [reducible] {T : Type₁} (self : Rc T) := handle.release self
+  definition Weak.release [reducible] {T : Type₁} (self : Weak T) := handle.release_weak self
+end rc
+
+namespace arc
+  -- same model as `Rc<T>`
+  definition Arc [reducible] := @rc.Rc
+  definition Weak [reducible] := @rc.Weak
+
+  definition Arc.release [reducible] := @rc.Rc.release
+  definition Weak.release [reducible] := @rc.Weak.release
+end arc
+
 /-
 namespace raw_vec
   definition RawVec (T : Type₁) := list (option T)
@@ -0,0 +1,153 @@
+import core.pre
+
+open bool
+open nat
+open prod.ops
+
+-- Fixed-width integers as bit vectors, imported by generated code with `int_repr = "bitvec"`.
+-- All operations are lifted from their `nat`/`int` counterparts in core/pre.lean, so that lemmas
+-- about those can be transported along `bv.to_nat`/`bv.to_int`.
+-- Note that the items axiomatized in core/pre.lean (e.g. slice `len`) still use `nat`, so calls into them
+-- are rejected, see the `int_repr` header of generated files.
+
+namespace bv
+  definition to_nat [reducible] {n : ℕ} (x : bitvec n) : nat := bitvec.toNat x
+
+  definition to_int [reducible] : Π {n : ℕ}, bitvec n → int
+  | 0        x := 0
+  | (succ n) x := bitvec.toInt x
+
+  definition of_nat [reducible] (n : ℕ) (x : nat) : bitvec n := bitvec.ofNat n x
+
+  definition of_int [reducible] : Π (n : ℕ), int → bitvec n
+  | 0        x := bitvec.zero 0
+  | (succ n) x := bitvec.ofInt n x
+
+  -- the right operand of shifts may be of a different width
+  section lift
+  variables {A : Type₁} {bits m : ℕ}
+
+  definition uu [reducible] (op : ℕ → nat → nat → A) (x : bitvec bits) (y : bitvec m) : A :=
+  op bits (to_nat x) (to_nat y)
+  definition us [reducible] (op : ℕ → nat → int → A) (x : bitvec bits) (y : bitvec m) : A :=
+  op bits (to_nat x) (to_int y)
+  definition su [reducible] (op : ℕ → int → nat → A) (x : bitvec bits) (y : bitvec m) : A :=
+  op bits (to_int x) (to_nat y)
+  definition ss [reducible] (op : ℕ → int → int → A) (x : bitvec bits) (y : bitvec m) : A :=
+  op bits (to_int x) (to_int y)
+
+  definition unsigned_res [reducible] (bits : ℕ) : sem nat → sem (bitvec bits) := sem.map (of_nat bits)
+  definition signed_res [reducible] (bits : ℕ) : sem int → sem (bitvec bits) := sem.map (of_int bits)
+  definition unsigned_flag_res [reducible] (bits : ℕ) : sem (nat × bool) → sem (bitvec bits × bool) :=
+  sem.map (λ p, (of_nat bits p.1, p.2))
+  definition signed_flag_res [reducible] (bits : ℕ) : sem (int × bool) → sem (bitvec bits × bool) :=
+  sem.map (λ p, (of_int bits p.1, p.2))
+  end lift
+end bv
+
+open bv
+
+definition bv.checked.add [reducible] (bits : ℕ) (x y : bitvec bits) := unsigned_res bits (uu checked.add x y)
+definition bv.checked.sub [reducible] (bits : ℕ) (x y : bitvec bits) := unsigned_res bits (uu checked.sub x y)
+definition bv.checked.mul [reducible] (bits : ℕ) (x y : bitvec bits) := unsigned_res bits (uu checked.mul x y)
+definition bv.checked.div [reducible] (bits : ℕ) (x y : bitvec bits) := unsigned_res bits (uu checked.div x y)
+definition bv.checked.rem [reducible] (bits : ℕ) (x y : bitvec bits) := unsigned_res bits (uu checked.rem x y)
+definition bv.checked.shl [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := unsigned_res bits (uu checked.shl x y)
+definition bv.checked.shls [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := unsigned_res bits (us checked.shls x y)
+definition bv.checked.shr [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := unsigned_res bits (uu checked.shr x y)
+definition bv.checked.shrs [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := unsigned_res bits (us checked.shrs x y)
+
+definition bv.checked.sadd [reducible] (bits : ℕ) (x y : bitvec bits) := signed_res bits (ss checked.sadd x y)
+definition bv.checked.ssub [reducible] (bits : ℕ) (x y : bitvec bits) := signed_res bits (ss checked.ssub x y)
+definition bv.checked.smul [reducible] (bits : ℕ) (x y : bitvec bits) := signed_res bits (ss checked.smul x y)
+definition bv.checked.sdiv [reducible] (bits : ℕ) (x y : bitvec bits) := signed_res bits (ss checked.sdiv x y)
+definition bv.checked.srem [reducible] (bits : ℕ) (x y : bitvec bits) := signed_res bits (ss checked.srem x y)
+definition bv.checked.sshl [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := signed_res bits (su checked.sshl x y)
+definition bv.checked.sshls [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := signed_res bits (ss checked.sshls x y)
+definition bv.checked.sshr [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := signed_res bits (su checked.sshr x y)
+definition bv.checked.sshrs [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := signed_res bits (ss checked.sshrs x y)
+definition bv.checked.neg [reducible] (bits : ℕ) (x : bitvec bits) := signed_res bits (checked.neg bits (to_int x))
+
+definition bv.wrapping.add [reducible] (bits : ℕ) (x y : bitvec bits) := of_nat bits (uu wrapping.add x y)
+definition bv.wrapping.sub [reducible] (bits : ℕ) (x y : bitvec bits) := of_nat bits (uu wrapping.sub x y)
+definition bv.wrapping.mul [reducible] (bits : ℕ) (x y : bitvec bits) := of_nat bits (uu wrapping.mul x y)
+definition bv.wrapping.shl [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := of_nat bits (uu wrapping.shl x y)
+definition bv.wrapping.shls [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := of_nat bits (us wrapping.shls x y)
+definition bv.wrapping.shr [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := of_nat bits (uu wrapping.shr x y)
+definition bv.wrapping.shrs [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := of_nat bits (us wrapping.shrs x y)
+
+definition bv.wrapping.sadd [reducible] (bits : ℕ) (x y : bitvec bits) := of_int bits (ss wrapping.sadd x y)
+definition bv.wrapping.ssub [reducible] (bits : ℕ) (x y : bitvec bits) := of_int bits (ss wrapping.ssub x y)
+definition bv.wrapping.smul [reducible] (bits : ℕ) (x y : bitvec bits) := of_int bits (ss wrapping.smul x y)
+definition bv.wrapping.sshl [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := of_int bits (su wrapping.sshl x y)
+definition bv.wrapping.sshls [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := of_int bits (ss wrapping.sshls x y)
+definition bv.wrapping.sshr [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := of_int bits (su wrapping.sshr x y)
+definition bv.wrapping.sshrs [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := of_int bits (ss wrapping.sshrs x y)
+definition bv.wrapping.neg [reducible] (bits : ℕ) (x : bitvec bits) := of_int bits (wrapping.neg bits (to_int x))
+
+definition bv.overflowing.add [reducible] (bits : ℕ) (x y : bitvec bits) := unsigned_flag_res bits (uu overflowing.add x y)
+definition bv.overflowing.sub [reducible] (bits : ℕ) (x y : bitvec bits) := unsigned_flag_res bits (uu overflowing.sub x y)
+definition bv.overflowing.mul [reducible] (bits : ℕ) (x y : bitvec bits) := unsigned_flag_res bits (uu overflowing.mul x y)
+definition bv.overflowing.div [reducible] (bits : ℕ) (x y : bitvec bits) := unsigned_flag_res bits (uu overflowing.div x y)
+definition bv.overflowing.rem [reducible] (bits : ℕ) (x y : bitvec bits) := unsigned_flag_res bits (uu overflowing.rem x y)
+definition bv.overflowing.shl [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := unsigned_flag_res bits (uu overflowing.shl x y)
+definition bv.overflowing.shls [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := unsigned_flag_res bits (us overflowing.shls x y)
+definition bv.overflowing.shr [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := unsigned_flag_res bits (uu overflowing.shr x y)
+definition bv.overflowing.shrs [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := unsigned_flag_res bits (us overflowing.shrs x y)
+
+definition bv.overflowing.sadd [reducible] (bits : ℕ) (x y : bitvec bits) := signed_flag_res bits (ss overflowing.sadd x y)
+definition bv.overflowing.ssub [reducible] (bits : ℕ) (x y : bitvec bits) := signed_flag_res bits (ss overflowing.ssub x y)
+definition bv.overflowing.smul [reducible] (bits : ℕ) (x y : bitvec bits) := signed_flag_res bits (ss overflowing.smul x y)
+definition bv.overflowing.sdiv [reducible] (bits : ℕ) (x y : bitvec bits) := signed_flag_res bits (ss overflowing.sdiv x y)
+definition bv.overflowing.srem [reducible] (bits : ℕ) (x y : bitvec bits) := signed_flag_res bits (ss overflowing.srem x y)
+definition bv.overflowing.sshl [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := signed_flag_res bits (su overflowing.sshl x y)
+definition bv.overflowing.sshls [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := signed_flag_res bits (ss overflowing.sshls x y)
+definition bv.overflowing.sshr [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := signed_flag_res bits (su overflowing.sshr x y)
+definition bv.overflowing.sshrs [reducible] (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := signed_flag_res bits (ss overflowing.sshrs x y)
+
+-- `as` casts truncate or extend, so unlike their `nat`/`int` counterparts they never fail
+namespace bv
+  definition unsigned_to_unsigned (bits : ℕ) {n : ℕ} (x : bitvec n) : sem (bitvec bits) := return (of_nat bits (to_nat x))
+  definition unsigned_to_signed (bits : ℕ) {n : ℕ} (x : bitvec n) : sem (bitvec bits) := return (of_nat bits (to_nat x))
+  definition signed_to_unsigned (bits : ℕ) {n : ℕ} (x : bitvec n) : sem (bitvec bits) := return (of_int bits (to_int x))
+  definition signed_to_signed (bits : ℕ) {n : ℕ} (x : bitvec n) : sem (bitvec bits) := return (of_int bits (to_int x))
+  definition bool_to_unsigned (bits : ℕ) (x : bool) : sem (bitvec bits) := return (of_nat bits (if x = tt then 1 else 0))
+  definition bool_to_signed (bits : ℕ) (x : bool) : sem (bitvec bits) := return (of_nat bits (if x = tt then 1 else 0))
+  definition char_to_unsigned (bits : ℕ) (x : char32) : sem (bitvec bits) := return (of_nat bits x)
+  definition char_to_signed (bits : ℕ) (x : char32) : sem (bitvec bits) := return (of_nat bits x)
+  -- statically checked by rustc by only allowing casts from `u8`
+  definition unsigned_to_char (bits : ℕ) {n : ℕ} (x : bitvec n) : sem char32 := return (to_nat x)
+  -- enum discriminants
+  definition int_to_unsigned (bits : ℕ) (x : int) : sem (bitvec bits) := return (of_int bits x)
+  definition int_to_signed (bits : ℕ) (x : int) : sem (bitvec bits) := return (of_int bits x)
+
+  -- `core.intrinsics.*_with_overflow` and `core.intrinsics.overflowing_*`
+  definition add_with_overflow (bits : ℕ) (x y : bitvec bits) := unsigned_flag_res bits (uu core.intrinsics.add_with_overflow x y)
+  definition sub_with_overflow (bits : ℕ) (x y : bitvec bits) := unsigned_flag_res bits (uu core.intrinsics.sub_with_overflow x y)
+  definition mul_with_overflow (bits : ℕ) (x y : bitvec bits) := unsigned_flag_res bits (uu core.intrinsics.mul_with_overflow x y)
+  definition add_with_overflow_signed (bits : ℕ) (x y : bitvec bits) := signed_flag_res bits (ss core.intrinsics.add_with_overflow_signed x y)
+  definition sub_with_overflow_signed (bits : ℕ) (x y : bitvec bits) := signed_flag_res bits (ss core.intrinsics.sub_with_overflow_signed x y)
+  definition mul_with_overflow_signed (bits : ℕ) (x y : bitvec bits) := signed_flag_res bits (ss core.intrinsics.mul_with_overflow_signed x y)
+
+  definition overflowing_add (bits : ℕ) (x y : bitvec bits) := unsigned_res bits (uu core.intrinsics.overflowing_add x y)
+  definition overflowing_sub (bits : ℕ) (x y : bitvec bits) := unsigned_res bits (uu core.intrinsics.overflowing_sub x y)
+  definition overflowing_mul (bits : ℕ) (x y : bitvec bits) := unsigned_res bits (uu core.intrinsics.overflowing_mul x y)
+  definition overflowing_add_signed (bits : ℕ) (x y : bitvec bits) := signed_res bits (ss core.intrinsics.overflowing_add_signed x y)
+  definition overflowing_sub_signed (bits : ℕ) (x y : bitvec bits) := signed_res bits (ss core.intrinsics.overflowing_sub_signed x y)
+  definition overflowing_mul_signed (bits : ℕ) (x y : bitvec bits) := signed_res bits (ss core.intrinsics.overflowing_mul_signed x y)
+
+  -- bit counting, swapping and rotation do not care about signedness
+  definition ctpop (bits : ℕ) (x : bitvec bits) := unsigned_res bits (core.intrinsics.ctpop bits (to_nat x))
+  definition ctlz (bits : ℕ) (x : bitvec bits) := unsigned_res bits (core.intrinsics.ctlz bits (to_nat x))
+  definition cttz (bits : ℕ) (x : bitvec bits) := unsigned_res bits (core.intrinsics.cttz bits (to_nat x))
+  definition bswap (bits : ℕ) (x : bitvec bits) := unsigned_res bits (core.intrinsics.bswap bits (to_nat x))
+  definition rotate_left (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := unsigned_res bits (uu core.intrinsics.rotate_left x y)
+  definition rotate_right (bits : ℕ) (x : bitvec bits) {m : ℕ} (y : bitvec m) := unsigned_res bits (uu core.intrinsics.rotate_right x y)
+
+  definition ctpop_signed [reducible] := ctpop
+  definition ctlz_signed [reducible] := ctlz
+  definition cttz_signed [reducible] := cttz
+  definition bswap_signed [reducible] := bswap
+  definition rotate_left_signed [reducible] := @rotate_left
+  definition rotate_right_signed [reducible] := @rotate_right
+end bv
@@ -2,6 +2,7 @@ import data.nat data.list
 import theories.topology.limit
 import bitvec
 import loop_combinator
+import core.target
 
 
 open bool
@@ -77,6 +78,7 @@ abbreviation u32 [parsing_only] := nat
 abbreviation u64 [parsing_only] := nat
 abbreviation usize [parsing_only] := nat
 
+-- Unicode scalar value, see `is_char`
 abbreviation char32 [parsing_only] := nat
 
 abbreviation i8 [parsing_only] := int
@@ -90,9 +92,11 @@ definition u16.bits [reducible] : ℕ := 16
 definition u32.bits [reducible] : ℕ := 32
 definition u64.bits [reducible] : ℕ := 64
 
--- Should perhaps be a constant-axiom pair, but that would break computability.
-definition usize.bits : ℕ := 16
+-- Generated from the rustc target. Proofs that should hold for any target must only rely on
+-- `usize.bits_ge_16`; target-specific ones can use `usize.bits_eq`.
+definition usize.bits : ℕ := core.target.pointer_width
 lemma usize.bits_ge_16 : usize.bits ≥ 16 := dec_trivial
+lemma usize.bits_eq : usize.bits = core.target.pointer_width := rfl
 attribute usize.bits [irreducible]
 
 definition char32.bits [reducible] : ℕ := 32
@@ -265,10 +269,117 @@ abbreviation slice [parsing_only] := list
 definition is_slice [class] [reducible] {T : Type₁} (xs : slice T) :=
 is_index (length xs)
 
+-- everything up to `0x10FFFF` except the UTF-16 surrogates
+definition is_char [class] [reducible] (c : char32) :=
+c < 0x110000 ∧ ¬(0xD800 ≤ c ∧ c < 0xE000)
+
+-- `str` is a byte slice holding valid UTF-8 (`is_str`)
+abbreviation str [parsing_only] := slice u8
+
+namespace utf8
+  definition encode (c : char32) : list u8 :=
+  if c < 0x80 then [c]
+  else if c < 0x800 then [0xC0 + c / 64, 0x80 + c % 64]
+  else if c < 0x10000 then [0xE0 + c / 4096, 0x80 + c / 64 % 64, 0x80 + c % 64]
+  else [0xF0 + c / 262144, 0x80 + c / 4096 % 64, 0x80 + c / 64 % 64, 0x80 + c % 64]
+
+  -- appends the payload of `n` continuation bytes
+  definition decode_cont : ℕ → ℕ → list u8 → option (char32 × list u8)
+  | 0        acc bs        := some (acc, bs)
+  | (succ n) acc []        := none
+  | (succ n) acc (b :: bs) := decode_cont n (acc * 64 + b % 64) bs
+
+  -- `core::str::next_code_point`, which assumes valid UTF-8
+  definition next_code_point : list u8 → option (char32 × list u8)
+  | []        := none
+  | (x :: xs) :=
+    if x < 0x80 then some (x, xs)
+    else if x < 0xE0 then decode_cont 1 (x % 32) xs
+    else if x < 0xF0 then decode_cont 2 (x % 16) xs
+    else decode_cont 3 (x % 8) xs
+
+  -- `chars` yields the encoded scalar values
+  lemma next_code_point_encode (c : char32) [is_char c] (bs : list u8) :
+    next_code_point (encode c ++ bs) = some (c, bs) :=
+  sorry
+end utf8
+
+definition is_str [class] (s : str) :=
+∃ cs : list char32, (∀ c, c ∈ cs → is_char c) ∧ s = join (map utf8.encode cs)
+
 definition wrap_signed (bits : ℕ) (a : int) : int := (a + 2^(bits-1)) % 2^bits - 2^(bits-1)
 definition overflowing_signed (op : int → int → int) (bits : ℕ) (a b : int) : sem (int × bool) :=
 return (wrap_signed bits (op a b), bool.of_Prop $ ¬is_bounded_int bits (op a b))
 
+-- release-mode arithmetic (`overflow_checks = false`)
+definition wrapping.add [reducible] (bits : ℕ) (x y : nat) : nat := (x + y) % 2^bits
+definition wrapping.sub [reducible] (bits : ℕ) (x y : nat) : nat := (x + 2^bits - y) % 2^bits
+definition wrapping.mul [reducible] (bits : ℕ) (x y : nat) : nat := x * y % 2^bits
+
+definition wrapping.sadd [reducible] (bits : ℕ) (x y : int) : int := wrap_signed bits (x + y)
+definition wrapping.ssub [reducible] (bits : ℕ) (x y : int) : int := wrap_signed bits (x - y)
+definition wrapping.smul [reducible] (bits : ℕ) (x y : int) : int := wrap_signed bits (x * y)
+definition wrapping.neg [reducible] (bits : ℕ) (x : int) : int := wrap_signed bits (-x)
+
+-- the shift amount is masked to the bit width
+definition wrapping.shl [reducible] (bits : ℕ) (x : nat) (y : u32) : nat :=
+unary_unsigned_bitwise_op bits (λ x, bitvec.shl x (y % bits)) x
+definition wrapping.shls [reducible] (bits : ℕ) (x : nat) (y : i32) : nat :=
+wrapping.shl bits x (nat.of_int (y % bits))
+definition wrapping.shr [reducible] (bits : ℕ) (x : nat) (y : u32) : nat :=
+x / 2^(y % bits)
+definition wrapping.shrs [reducible] (bits : ℕ) (x : nat) (y : i32) : nat :=
+wrapping.shr bits x (nat.of_int (y % bits))
+
+definition wrapping.sshl [reducible] (bits : ℕ) (x : int) (y : u32) : int :=
+unary_signed_bitwise_op bits (λ n x, bitvec.shl x (y % bits)) x
+definition wrapping.sshls [reducible] (bits : ℕ) (x : int) (y : i32) : int :=
+wrapping.sshl bits x (nat.of_int (y % bits))
+definition wrapping.sshr [reducible] (bits : ℕ) (x : int) (y : u32) : int :=
+unary_signed_bitwise_op bits (λ n x, bitvec.sshr x (y % bits)) x
+definition wrapping.sshrs [reducible] (bits : ℕ) (x : int) (y : i32) : int :=
+wrapping.sshr bits x (nat.of_int (y % bits))
+
+-- wrapped result and overflow flag of `CheckedBinaryOp`s
+definition overflowing.add [reducible] (bits : ℕ) (x y : nat) : sem (nat × bool) :=
+return (wrapping.add bits x y, x + y ≥ᵇ 2^bits)
+definition overflowing.sub [reducible] (bits : ℕ) (x y : nat) : sem (nat × bool) :=
+return (wrapping.sub bits x y, x <ᵇ y)
+definition overflowing.mul [reducible] (bits : ℕ) (x y : nat) : sem (nat × bool) :=
+return (wrapping.mul bits x y, x * y ≥ᵇ 2^bits)
+
+definition overflowing.sadd [reducible] := overflowing_signed add
+definition overflowing.ssub [reducible] := overflowing_signed sub
+definition overflowing.smul [reducible] := overflowing_signed mul
+
+definition overflowing.shl [reducible] (bits : ℕ) (x : nat) (y : u32) : sem (nat × bool) :=
+return (wrapping.shl bits x y, y ≥ᵇ bits)
+definition overflowing.shls [reducible] (bits : ℕ) (x : nat) (y : i32) : sem (nat × bool) :=
+return (wrapping.shls bits x y, bool.of_Prop $ ¬(0 ≤ y ∧ y < bits))
+definition overflowing.shr [reducible] (bits : ℕ) (x : nat) (y : u32) : sem (nat × bool) :=
+return (wrapping.shr bits x y, y ≥ᵇ bits)
+definition overflowing.shrs [reducible] (bits : ℕ) (x : nat) (y : i32) : sem (nat × bool) :=
+return (wrapping.shrs bits x y, bool.of_Prop $ ¬(0 ≤ y ∧ y < bits))
+
+definition overflowing.sshl [reducible] (bits : ℕ) (x : int) (y : u32) : sem (int × bool) :=
+return (wrapping.sshl bits x y, y ≥ᵇ bits)
+definition overflowing.sshls [reducible] (bits : ℕ) (x : int) (y : i32) : sem (int × bool) :=
+return (wrapping.sshls bits x y, bool.of_Prop $ ¬(0 ≤ y ∧ y < bits))
+definition overflowing.sshr [reducible] (bits : ℕ) (x : int) (y : u32) : sem (int × bool) :=
+return (wrapping.sshr bits x y, y ≥ᵇ bits)
+definition overflowing.sshrs [reducible] (bits : ℕ) (x : int) (y : i32) : sem (int × bool) :=
+return (wrapping.sshrs bits x y, bool.of_Prop $ ¬(0 ≤ y ∧ y < bits))
+
+-- division by zero panics even without overflow checks, so these are never emitted as `wrapping.*`
+definition overflowing.div [reducible] (bits : ℕ) (x y : nat) : sem (nat × bool) :=
+sem.map (λ z, (z, ff)) (checked.div bits x y)
+definition overflowing.rem [reducible] (bits : ℕ) (x y : nat) : sem (nat × bool) :=
+sem.map (λ z, (z, ff)) (checked.rem bits x y)
+definition overflowing.sdiv [reducible] (bits : ℕ) (x y : int) : sem (int × bool) :=
+sem.guard (y ≠ 0) $ overflowing_signed div bits x y
+definition overflowing.srem [reducible] (bits : ℕ) (x y : int) : sem (int × bool) :=
+sem.guard (y ≠ 0) $ overflowing_signed mod bits x y
+
 namespace core
   namespace intrinsics
     definition add_with_overflow (bits : ℕ) (a b : nat) : sem (nat × bool) :=
@@ -289,6 +400,40 @@ namespace core
     definition overflowing_add_signed (bits : ℕ) (a b : int) := sem.map prod.pr1 (overflowing_signed add bits a b)
     definition overflowing_sub_signed (bits : ℕ) (a b : int) := sem.map prod.pr1 (overflowing_signed sub bits a b)
     definition overflowing_mul_signed (bits : ℕ) (a b : int) := sem.map prod.pr1 (overflowing_signed mul bits a b)
+
+    -- bit counting on the lowest `n` bits
+    definition ctpop_aux : ℕ → nat → nat
+    | 0        x := 0
+    | (succ n) x := x % 2 + ctpop_aux n (x / 2)
+    definition cttz_aux : ℕ → nat → nat
+    | 0        x := 0
+    | (succ n) x := if x % 2 = 1 then 0 else succ (cttz_aux n (x / 2))
+    definition width_aux : ℕ → nat → nat
+    | 0        x := 0
+    | (succ n) x := if x = 0 then 0 else succ (width_aux n (x / 2))
+    definition bswap_aux : ℕ → nat → nat → nat
+    | 0        x acc := acc
+    | (succ n) x acc := bswap_aux n (x / 256) (acc * 256 + x % 256)
+
+    definition ctpop (bits : ℕ) (x : nat) : sem nat := return (ctpop_aux bits x)
+    definition ctlz (bits : ℕ) (x : nat) : sem nat := return (bits - width_aux bits x)
+    definition cttz (bits : ℕ) (x : nat) : sem nat := return (cttz_aux bits x)
+    definition bswap (bits : ℕ) (x : nat) : sem nat := return (bswap_aux (bits / 8) x 0)
+    definition rotate_left (bits : ℕ) (x : nat) (n : u32) : sem nat :=
+    return ((x * 2^(n % bits) + x / 2^(bits - n % bits)) % 2^bits)
+    definition rotate_right (bits : ℕ) (x : nat) (n : u32) : sem nat :=
+    rotate_left bits x (bits - n % bits)
+
+    -- signed variants act on the two's complement representation
+    definition on_signed [reducible] (op : ℕ → nat → sem nat) (bits : ℕ) (x : int) : sem int :=
+    sem.map (λ y, wrap_signed bits y) (op bits (nat.of_int (x % 2^bits)))
+
+    definition ctpop_signed := on_signed ctpop
+    definition ctlz_signed := on_signed ctlz
+    definition cttz_signed := on_signed cttz
+    definition bswap_signed := on_signed bswap
+    definition rotate_left_signed (bits : ℕ) (x : int) (n : u32) := on_signed (λ bits x, rotate_left bits x n) bits x
+    definition rotate_right_signed (bits : ℕ) (x : int) (n : u32) := on_signed (λ bits x, rotate_right bits x n) bits x
   end intrinsics
 
   abbreviation mem.swap {T : Type₁} (x y : T) : sem (unit × T × T) := return (unit.star,y,x)
@@ -299,6 +444,20 @@ namespace core
     : sem T :=
   sem.lift_opt (list.nth self index)
 
+  -- `str` is a byte slice, see `is_str`
+  definition «str as core.str.StrExt».as_bytes (self : str) : sem (slice u8) := return self
+
+  structure str.Chars := mk ::
+  (bytes : str)
+
+  definition «str as core.str.StrExt».chars (self : str) : sem str.Chars := return (str.Chars.mk self)
+  definition «core.str.Chars<'a> as core.iter.iterator.Iterator».next (self : str.Chars) :
+    sem (option.Option char32 × str.Chars) :=
+  return (match utf8.next_code_point (str.Chars.bytes self) with
+  | some (c, bytes) := (option.Option.Some c, str.Chars.mk bytes)
+  | none            := (option.Option.None, self)
+  end)
+
   /- This trait has way too many freaky dependencies -/
   structure fmt.Debug [class] (Self : Type₁) := mk ::
 
@@ -324,6 +483,56 @@ namespace core
   definition isize.overflowing_shr (a : int) (b : nat) :=
   return (unary_signed_bitwise_op isize.bits (λ n a, bitvec.sshr a b) (a % isize.bits), a ≥ᵇ isize.bits)
 
+  -- Interior mutability: `&Cell<T>` and `&RefCell<T>` are passed and returned like `&mut`, and a
+  -- `RefCell` guard is a lens to its `RefCell` that gives back the borrow when dropped.
+  namespace cell
+    structure Cell (T : Type₁) := mk {} ::
+    (value : T)
+
+    definition «Cell<T>».new {T : Type₁} (value : T) : sem (Cell T) := return (Cell.mk value)
+    definition «Cell<T>».get {T : Type₁} (self : Cell T) : sem (T × Cell T) := return (Cell.value self, self)
+    definition «Cell<T>».set {T : Type₁} (self : Cell T) (value : T) : sem (unit × Cell T) :=
+    return (unit.star, Cell.mk value)
+    definition «Cell<T>».get_mut {T : Type₁} (self : Cell T) : sem (lens (Cell T) T × Cell T) :=
+    return (lens.mk (return ∘ Cell.value) (λ o i, return (Cell.mk i)), self)
+
+    -- number of live `Ref`s and whether there is a live `RefMut`
+    structure RefCell (T : Type₁) := mk {} ::
+    (value : T)
+    (readers : ℕ)
+    (writing : bool)
+
+    definition RefCell.value_lens [constructor] {T : Type₁} : lens (RefCell T) T :=
+    lens.mk (return ∘ RefCell.value) (λ o i, return ⦃ RefCell, value := i, o ⦄)
+
+    definition «RefCell<T>».new {T : Type₁} (value : T) : sem (RefCell T) := return (RefCell.mk value 0 ff)
+    definition «RefCell<T>».into_inner {T : Type₁} (self : RefCell T) : sem T := return (RefCell.value self)
+    definition «RefCell<T>».get_mut {T : Type₁} (self : RefCell T) : sem (lens (RefCell T) T × RefCell T) :=
+    return (RefCell.value_lens, self)
+
+    definition «RefCell<T>».borrow {T : Type₁} (self : RefCell T) : sem (lens (RefCell T) (RefCell T) × RefCell T) :=
+    sem.assert panic_reason.borrow (bool.bnot (RefCell.writing self)) $
+    return (lens.id, ⦃ RefCell, readers := RefCell.readers self + 1, self ⦄)
+    definition «RefCell<T>».borrow_mut {T : Type₁} (self : RefCell T) : sem (lens (RefCell T) (RefCell T) × RefCell T) :=
+    sem.assert panic_reason.borrow (band (bool.bnot (RefCell.writing self)) (RefCell.readers self =ᵇ 0)) $
+    return (lens.id, ⦃ RefCell, writing := tt, self ⦄)
+
+    definition Ref.release {Outer T : Type₁} (guard : lens Outer (RefCell T)) (o : Outer) : sem Outer :=
+    do c ← lens.get guard o;
+    lens.set guard o ⦃ RefCell, readers := RefCell.readers c - 1, c ⦄
+    definition RefMut.release {Outer T : Type₁} (guard : lens Outer (RefCell T)) (o : Outer) : sem Outer :=
+    do c ← lens.get guard o;
+    lens.set guard o ⦃ RefCell, writing := ff, c ⦄
+
+    definition «core.cell.Ref<'b, T> as core.ops.Deref».deref {T : Type₁} (self : RefCell T) : sem (T × RefCell T) :=
+    return (RefCell.value self, self)
+    definition «core.cell.RefMut<'b, T> as core.ops.Deref».deref {T : Type₁} (self : RefCell T) : sem (T × RefCell T) :=
+    return (RefCell.value self, self)
+    definition «core.cell.RefMut<'b, T> as core.ops.DerefMut».deref_mut {T : Type₁} (self : RefCell T) :
+      sem (lens (RefCell T) T × RefCell T) :=
+    return (RefCell.value_lens, self)
+  end cell
+
   namespace ops
     structure FnOnce [class] (Self : Type₁) (Args : Type₁) (Output : Type₁) :=
     (call_once : Self → Args → sem Output)
@@ -0,0 +1,7 @@
+-- generated for target x86_64-unknown-linux-gnu
+
+namespace core.target
+definition triple := "x86_64-unknown-linux-gnu"
+definition pointer_width [reducible] : ℕ := 64
+definition little_endian [reducible] : bool := tt
+end core.target
@@ -0,0 +1,73 @@
+import core.pre
+
+open nat
+open option
+open prod.ops
+
+-- Raw pointers as addresses into an explicit heap, imported by generated code with `heap_model = true`.
+-- Functions using the heap take it as an additional last parameter `heap__` and return it as the last
+-- component of their result, just like a `&mut` parameter.
+
+-- (allocation block, index in units of `T`); block 0 is never allocated
+definition ptr [reducible] (T : Type₁) := ℕ × ℕ
+
+definition ptr.null [reducible] {T : Type₁} : ptr T := (0, 0)
+-- Indices are counted in units of the element type, so a pointer can only be cast to another element type at
+-- the start of its block. Casts between `*const T` and `*mut T` leave the pointer unchanged.
+definition ptr.cast [reducible] {T U : Type₁} (p : ptr T) : option (ptr U) :=
+if p.2 = 0 then some (p.1, 0) else none
+definition ptr.offset [reducible] {T : Type₁} (p : ptr T) (n : isize) : option (ptr T) :=
+if (p.2 : ℤ) + n ≥ 0 then some (p.1, nat.of_int (p.2 + n)) else none
+
+-- The heap is kept abstract; its axioms describe reads after writes through pointers of the same type.
+-- Every access returns `none` when it is invalid, i.e. out of bounds, unaligned, uninitialized or freed.
+constant heap : Type₁
+constant heap.read {T : Type₁} : heap → ptr T → option T
+constant heap.write {T : Type₁} : heap → ptr T → T → option heap
+-- fresh block of `size` bytes with alignment `align`
+constant heap.alloc : heap → ℕ → ℕ → option (ptr u8 × heap)
+constant heap.realloc : heap → ptr u8 → ℕ → ℕ → ℕ → option (ptr u8 × heap)
+constant heap.dealloc : heap → ptr u8 → ℕ → ℕ → option heap
+
+axiom heap.read_write {T : Type₁} {h h' : heap} {p : ptr T} {x : T} :
+  heap.write h p x = some h' → heap.read h' p = some x
+axiom heap.read_write_ne {T : Type₁} {h h' : heap} {p q : ptr T} {x : T} :
+  heap.write h p x = some h' → p ≠ q → heap.read h' q = heap.read h q
+axiom heap.alloc_fresh {T : Type₁} {h h' : heap} {size align : ℕ} {p : ptr u8} {q : ptr T} :
+  heap.alloc h size align = some (p, h') → q.1 ≠ p.1 → heap.read h' q = heap.read h q
+
+namespace heap
+  definition read_list {T : Type₁} (h : heap) : ℕ → ptr T → option (list T)
+  | 0        p := some []
+  | (succ n) p := option.bind (heap.read h p) (λ x, option.map (list.cons x) (read_list n (p.1, p.2 + 1)))
+
+  definition write_list {T : Type₁} : heap → ptr T → list T → option heap
+  | h p []        := some h
+  | h p (x :: xs) := option.bind (heap.write h p x) (λ h', write_list h' (p.1, p.2 + 1) xs)
+
+  -- models of the primitives in `trans/fun/intrinsics.rs`, all taking the heap last
+  definition allocate (size align : usize) (h : heap) : sem (ptr u8 × heap) :=
+  sem.lift_opt (heap.alloc h size align)
+  definition reallocate (p : ptr u8) (old_size size align : usize) (h : heap) : sem (ptr u8 × heap) :=
+  sem.lift_opt (heap.realloc h p old_size size align)
+  definition deallocate (p : ptr u8) (old_size align : usize) (h : heap) : sem (unit × heap) :=
+  sem.map (λ h', (unit.star, h')) (sem.lift_opt (heap.dealloc h p old_size align))
+
+  definition null {T : Type₁} (h : heap) : sem (ptr T × heap) := return (ptr.null, h)
+  definition is_null {T : Type₁} (p : ptr T) (h : heap) : sem (bool × heap) := return (p =ᵇ ptr.null, h)
+  definition offset {T : Type₁} (p : ptr T) (n : isize) (h : heap) : sem (ptr T × heap) :=
+  sem.map (λ p', (p', h)) (sem.lift_opt (ptr.offset p n))
+
+  definition ptr_read {T : Type₁} (p : ptr T) (h : heap) : sem (T × heap) :=
+  sem.map (λ x, (x, h)) (sem.lift_opt (heap.read h p))
+  definition ptr_write {T : Type₁} (p : ptr T) (x : T) (h : heap) : sem (unit × heap) :=
+  sem.map (λ h', (unit.star, h')) (sem.lift_opt (heap.write h p x))
+
+  -- reads all elements before writing any, so overlapping ranges are fine
+  definition copy {T : Type₁} (src dst : ptr T) (n : usize) (h : heap) : sem (unit × heap) :=
+  do xs ← sem.lift_opt (read_list h n src);
+  sem.map (λ h', (unit.star, h')) (sem.lift_opt (write_list h dst xs))
+
+  definition copy_nonoverlapping {T : Type₁} (src dst : ptr T) (n : usize) (h : heap) : sem (unit × heap) :=
+  sem.guard (src.1 ≠ dst.1 ∨ src.2 + n ≤ dst.2 ∨ dst.2 + n ≤ src.2) $ copy src dst n h
+end heap
@@ -0,0 +1,70 @@
+import core.pre
+
+open bool
+open list
+open nat
+open option
+open prod.ops
+
+-- Byte encodings of values as laid out by rustc, imported by generated code with `layout_model = true`.
+-- Each translatable type gets an encoder `T → list u8` and a partial decoder `list u8 → option T`
+-- that fails on bytes not representing a valid value. Unions are modelled as their raw bytes, so union
+-- field reads and `transmute` become `decode (encode x)`.
+
+namespace layout
+
+-- little-endian bytes of `n` mod 256^size
+definition le_bytes : ℕ → ℕ → list u8
+| 0            n := []
+| (succ size) n := n % 256 :: le_bytes size (n / 256)
+
+definition of_le_bytes : list u8 → ℕ
+| []        := 0
+| (b :: bs) := b + 256 * of_le_bytes bs
+
+definition order_bytes (bs : list u8) : list u8 :=
+if core.target.little_endian = tt then bs else reverse bs
+
+definition encode_nat (size n : ℕ) : list u8 := order_bytes (le_bytes size n)
+definition decode_nat (size : ℕ) (bs : list u8) : option ℕ :=
+if length bs = size then some (of_le_bytes (order_bytes bs)) else none
+
+-- two's complement
+definition encode_int (size : ℕ) (i : ℤ) : list u8 :=
+encode_nat size (nat.of_int (i % 2^(8 * size)))
+definition decode_int (size : ℕ) (bs : list u8) : option ℤ :=
+option.map (λ n, if n < 2^(8 * size - 1) then (n : ℤ) else n - 2^(8 * size)) (decode_nat size bs)
+
+definition encode_bool (b : bool) : list u8 := [if b = tt then 1 else 0]
+definition decode_bool : list u8 → option bool
+| [0] := some ff
+| [1] := some tt
+| _   := none
+
+definition encode_char (c : char32) : list u8 := encode_nat 4 c
+definition decode_char (bs : list u8) : option char32 :=
+option.bind (decode_nat 4 bs) (λ c, if is_char c then some c else none)
+
+definition raw (size : ℕ) (bs : list u8) : option (list u8) :=
+if length bs = size then some bs else none
+
+definition encode_array {T : Type₁} (enc : T → list u8) (xs : list T) : list u8 :=
+join (map enc xs)
+definition decode_array {T : Type₁} (size : ℕ) (dec : list u8 → option T) : ℕ → list u8 → option (list T)
+| 0        bs := if bs = [] then some [] else none
+| (succ n) bs := option.bind (dec (firstn size bs)) (λ x,
+  option.map (cons x) (decode_array n (dropn size bs)))
+
+-- the `size` bytes starting at `offset`
+definition slice (offset size : ℕ) (bs : list u8) : list u8 :=
+firstn size (dropn offset bs)
+
+-- replaces the bytes starting at `offset` with `part`
+definition overwrite (offset : ℕ) (part bs : list u8) : list u8 :=
+firstn offset bs ++ part ++ dropn (offset + length part) bs
+
+-- `size` bytes holding each `(offset, part)`; padding is zeroed
+definition place (size : ℕ) (parts : list (ℕ × list u8)) : list u8 :=
+foldl (λ bs p, overwrite p.1 p.2 bs) (replicate size 0) parts
+
+end layout
@@ -188,6 +188,21 @@ section
     rewrite [-loop.fix_eq_loop this],
     apply t,
   end
+
+  -- for loops annotated with `invariant!`/`variant!`, whose `.preserve` and `.decrease` lemmas imply `step`
+  protected theorem loop.terminates_with_variant
+    (v : State → ℕ)
+    (s : State)
+    (p : State → Prop)
+    (q : Res → Prop)
+    (start : p s)
+    (step : ∀ s, p s → match body s with
+      | some (inl s', _) := p s' ∧ v s' < v s
+      | some (inr r, _)  := q r
+      | none := false
+      end) :
+    sem.terminates_with q (loop s) :=
+  @loop.terminates_with (measure v) (measure.wf v) s p q start step
 end
 
 section
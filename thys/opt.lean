@@ -0,0 +1,67 @@
+import loop_combinator
+
+open option
+open prod.ops
+
+-- plain partiality monad without step counting, selected by
+--
+--   [monad]
+--   import = "opt"
+--   type = "opt"
+--   bind = "optdo"
+--   step = "optdo"
+--   incr = "opt.incr"
+--   return = "opt.return"
+--   fail = "none"
+--   lift = "opt.lift"
+--   map = "option.map"
+--   loop = "opt.loop"
+--   panic = "opt.panic"
+--   assert = "opt.assert"
+--   guard = "opt.guard"
+--   returns = "opt.returns"
+--   terminates = "opt.terminates"
+--   terminates_with = "opt.terminates_with"
+--   on_return = "opt.on_return"
+
+definition opt (a : Type₁) := option a
+
+definition opt.return [unfold 2] {a : Type₁} (x : a) : opt a := some x
+definition opt.bind [unfold 3] {a b : Type₁} (m : opt a) (f : a → opt b) : opt b :=
+option.rec none f m
+
+notation `optdo ` binder ` ← ` x `; ` r:(scoped f, opt.bind x f) := r
+
+-- no costs are counted
+definition opt.incr [unfold 3] {a : Type₁} (n : ℕ) (m : opt a) : opt a := m
+
+definition opt.lift [unfold 2] {a : Type₁} (m : option a) : opt a := m
+
+-- calls into dependencies translated with the default `sem` monad (e.g. core) forget their step count
+definition opt.of_sem [coercion] [unfold 2] {a : Type₁} (m : sem a) : opt a := option.map pr₁ m
+
+definition opt.guard [reducible] {a : Type₁} (p : Prop) [decidable p] (m : opt a) : opt a :=
+if p then m else none
+
+definition opt.panic [unfold 2] {a : Type₁} (r : panic_reason) : opt a := none
+
+definition opt.assert [reducible] {a : Type₁} (r : panic_reason) (c : bool) (m : opt a) : opt a :=
+if c = tt then m else opt.panic r
+
+-- reuses the `sem` loop combinator, forgetting the step count
+noncomputable definition opt.loop {State Res : Type₁} (body : State → opt (State + Res)) (s : State) : opt Res :=
+option.map pr₁ (loop (λ s, sem.lift_opt (body s)) s)
+
+definition opt.terminates_with [unfold 3] {a : Type₁} (H : a → Prop) : opt a → Prop
+| (some x) := H x
+| none     := false
+
+abbreviation opt.returns [unfold 3] {a : Type₁} (x : a) := opt.terminates_with (λ a, a = x)
+
+definition opt.terminates [unfold 2] {a : Type₁} (m : opt a) : Prop :=
+opt.terminates_with (λ a, true) m
+
+-- partial correctness, see `sem.on_return`
+definition opt.on_return [unfold 3] {a : Type₁} (H : a → Prop) : opt a → Prop
+| (some x) := H x
+| none     := true
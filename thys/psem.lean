@@ -0,0 +1,101 @@
+import loop_combinator
+
+open option
+open sum
+
+-- `sem` whose panics carry their `panic_reason`, the default vocabulary with `panic_reasons = true`:
+--
+--   [monad]
+--   import = "psem"
+--   type = "psem"
+--   bind = "pdo"
+--   step = "pdostep"
+--   incr = "psem.incr"
+--   return = "psem.return"
+--   fail = "psem.zero"
+--   lift = "psem.lift"
+--   map = "psem.map"
+--   loop = "psem.loop"
+--   panic = "psem.panic"
+--   assert = "psem.assert"
+--   guard = "psem.guard"
+--   returns = "psem.returns"
+--   terminates = "psem.terminates"
+--   terminates_with = "psem.terminates_with"
+--   on_return = "psem.on_return"
+--
+-- `none` is nontermination or a failure without a reason, e.g. of the loop combinator.
+
+definition psem (a : Type₁) := option (panic_reason + a × ℕ)
+
+definition psem.return [unfold 2] {a : Type₁} (x : a) : psem a := some (inr (x, 0))
+definition psem.zero {a : Type₁} : psem a := none
+definition psem.panic [unfold 2] {a : Type₁} (r : panic_reason) : psem a := some (inl r)
+
+definition psem.incr [unfold 3] {a : Type₁} (n : ℕ) : psem a → psem a
+| none                := none
+| (some (inl r))      := some (inl r)
+| (some (inr (x, k))) := some (inr (x, k+n))
+
+definition psem.bind [unfold 3] {a b : Type₁} : psem a → (a → psem b) → psem b
+| none                f := none
+| (some (inl r))      f := psem.panic r
+| (some (inr (x, k))) f := psem.incr k (f x)
+
+notation `pdo ` binder ` ← ` x `; ` r:(scoped f, psem.bind x f) := r
+notation `pdostep ` binder ` ← ` x `; ` r:(scoped f, psem.incr 1 (psem.bind x f)) := r
+
+definition psem.map [unfold 4] {a b : Type₁} (f : a → b) (m : psem a) : psem b :=
+psem.bind m (λ x, psem.return (f x))
+
+definition psem.lift [unfold 2] {a : Type₁} : option a → psem a :=
+option.rec psem.zero psem.return
+
+-- primitives of core/pre.lean and callees translated with `sem` keep their result and cost, but fail without
+-- a reason
+definition psem.of_sem [coercion] [unfold 2] {a : Type₁} : sem a → psem a :=
+option.rec psem.zero (λ p, some (inr p))
+
+definition psem.guard [reducible] {a : Type₁} (p : Prop) [decidable p] (s : psem a) : psem a :=
+if p then s else psem.zero
+
+definition psem.assert [reducible] {a : Type₁} (r : panic_reason) (c : bool) (s : psem a) : psem a :=
+if c = tt then s else psem.panic r
+
+-- reuses the `sem` loop combinator, passing panics of the body through as results
+definition psem.loop_step [unfold 3] {State Res : Type₁} : psem (State + Res) → sem (State + (panic_reason + Res))
+| none                      := none
+| (some (inl r))            := some (inr (inl r), 0)
+| (some (inr (inl s', k)))  := some (inl s', k)
+| (some (inr (inr x, k)))   := some (inr (inr x), k)
+
+definition psem.loop_result [unfold 2] {Res : Type₁} : sem (panic_reason + Res) → psem Res
+| none                  := none
+| (some (inl r, k))     := psem.panic r
+| (some (inr x, k))     := some (inr (x, k))
+
+noncomputable definition psem.loop {State Res : Type₁} (body : State → psem (State + Res)) (s : State) : psem Res :=
+psem.loop_result (loop (λ s, psem.loop_step (body s)) s)
+
+definition psem.terminates_with [unfold 3] {a : Type₁} (H : a → Prop) : psem a → Prop
+| (some (inr (x, k))) := H x
+| (some (inl r))      := false
+| none                := false
+
+abbreviation psem.returns [unfold 3] {a : Type₁} (x : a) := psem.terminates_with (λ a, a = x)
+
+definition psem.terminates [unfold 2] {a : Type₁} (s : psem a) : Prop :=
+psem.terminates_with (λ a, true) s
+
+-- partial correctness, see `sem.on_return`
+definition psem.on_return [unfold 3] {a : Type₁} (H : a → Prop) : psem a → Prop
+| (some (inr (x, k))) := H x
+| (some (inl r))      := true
+| none                := true
+
+-- `s` panics at most for reasons satisfying `P`, e.g. `psem.panics_only (λ r, r = panic_reason.bounds_check) s`
+-- states that `s` never panics on overflow, independently of whether it terminates
+definition psem.panics_only [unfold 3] {a : Type₁} (P : panic_reason → Prop) : psem a → Prop
+| (some (inl r)) := P r
+| (some (inr p)) := true
+| none           := true
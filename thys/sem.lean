@@ -24,6 +24,10 @@ abbreviation sem.returns [unfold 3] {a : Type₁} (x : a) := sem.terminates_with
 definition sem.terminates [unfold 2] {a : Type₁} (s : sem a) : Prop :=
 sem.terminates_with (λ a, true) s
 
+-- partial correctness, e.g. for preservation of type invariants
+definition sem.on_return [unfold 3] {a : Type₁} (H : a → Prop) (s : sem a) : Prop :=
+∀ x k, s = some (x, k) → H x
+
 lemma sem.terminates_with_incr {a : Type₁} {p : a → Prop} (k : ℕ) {s : sem a}
   (h : sem.terminates_with p s) : sem.terminates_with p (sem.incr k s) :=
 begin
@@ -116,6 +120,28 @@ option.rec sem.zero return
 definition sem.guard [reducible] {a : Type₁} (p : Prop) [decidable p] (s : sem a) : sem a :=
 if p then s else mzero
 
+-- reasons for panicking, emitted instead of plain `mzero` with `panic_reasons = true`, which selects the
+-- `psem` monad (psem.lean) by default
+inductive panic_reason :=
+| bounds_check {} : panic_reason
+| overflow {} : string → panic_reason -- name of the overflowing operation
+| div_by_zero {} : panic_reason
+| rem_by_zero {} : panic_reason
+| math {} : string → panic_reason -- any other arithmetic error
+| explicit {} : string → panic_reason -- diverging call, e.g. `panic!`, with its call site
+| unreachable {} : panic_reason
+| abort {} : panic_reason -- `core.intrinsics.abort`
+| borrow {} : panic_reason -- conflicting `RefCell` borrows
+
+-- the reason is not observable in `sem`; `psem.panic` keeps it
+definition sem.panic [unfold 2] {a : Type₁} (r : panic_reason) : sem a := mzero
+
+definition sem.assert [reducible] {a : Type₁} (r : panic_reason) (c : bool) (s : sem a) : sem a :=
+if c = tt then s else sem.panic r
+
+lemma sem.assert_tt {a : Type₁} {r : panic_reason} {s : sem a} : sem.assert r tt s = s :=
+if_pos rfl
+
 attribute sem.bind [unfold 3]
 attribute sem.return [constructor]
 